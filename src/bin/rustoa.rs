@@ -0,0 +1,403 @@
+//! The `rustoa` command-line tool.
+//!
+//! ```text
+//! rustoa team 16405
+//! rustoa team 16405 events --season 1920
+//! rustoa event 1920-TX-TRQ rankings
+//! rustoa match 1920-TX-TRQ-Q001-1 --format json
+//! rustoa version
+//! ```
+//!
+//! The API key is read from `--api-key`, then the `TOA_API_KEY` environment variable, then
+//! an `api_key = ...` line in the config file (`~/.config/rustoa/config` by default).
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rustoa::{CachePolicy, Client, Match, Season};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SEASONS: [&str; 9] = [
+    "2425", "2324", "2223", "2122", "2021", "1920", "1819", "1718", "1617",
+];
+
+#[derive(Parser)]
+#[command(
+    name = "rustoa",
+    version,
+    about = "Query The Orange Alliance from the command line"
+)]
+struct Cli {
+    /// The TOA API key
+    #[arg(long, env = "TOA_API_KEY", hide_env_values = true, global = true)]
+    api_key: Option<String>,
+    /// A config file with an `api_key = ...` line [default: ~/.config/rustoa/config]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// How to print the results
+    #[arg(long, value_enum, default_value = "table", global = true)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show a team's details, or one of its lists
+    Team {
+        number: u32,
+        #[command(subcommand)]
+        list: Option<TeamList>,
+    },
+    /// Show an event's details, or one of its lists
+    Event {
+        key: String,
+        #[command(subcommand)]
+        list: Option<EventList>,
+    },
+    /// Show a single match
+    Match { key: String },
+    /// Show the versions of rustoa and of the API
+    Version,
+}
+
+#[derive(Subcommand)]
+enum TeamList {
+    /// The events the team attended in a season
+    Events {
+        #[arg(long, default_value = "1920", value_parser = SEASONS)]
+        season: String,
+    },
+    /// The team's result at each event of a season
+    Results {
+        #[arg(long, default_value = "1920", value_parser = SEASONS)]
+        season: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum EventList {
+    /// The qualification rankings
+    Rankings,
+    /// Every match of the event
+    Matches,
+}
+
+/// The result of a command, ready to be printed in any format.
+struct Output {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    json: serde_json::Value,
+}
+
+impl Output {
+    /// A two-column table of the fields of a single record.
+    fn fields(map: HashMap<String, String>) -> Output {
+        let mut rows: Vec<Vec<String>> = map.into_iter().map(|(k, v)| vec![k, v]).collect();
+        rows.sort();
+        let json = rows
+            .iter()
+            .map(|row| (row[0].clone(), serde_json::Value::from(&row[1][..])))
+            .collect();
+        Output {
+            headers: vec!["field", "value"],
+            rows,
+            json: serde_json::Value::Object(json),
+        }
+    }
+
+    fn render(&self, format: Format) -> Result<String, Box<dyn Error>> {
+        match format {
+            Format::Json => Ok(serde_json::to_string_pretty(&self.json)? + "\n"),
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(&self.headers)?;
+                for row in self.rows.iter() {
+                    writer.write_record(row)?;
+                }
+                Ok(String::from_utf8(writer.into_inner()?)?)
+            }
+            Format::Table => Ok(table(&self.headers, &self.rows)),
+        }
+    }
+}
+
+/// Render rows as a plain-text table with aligned columns.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        padded.join("  ").trim_end().to_string() + "\n"
+    };
+    let mut out = line(headers.to_vec());
+    let rules: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    out += &line(rules.iter().map(|r| &r[..]).collect());
+    for row in rows.iter() {
+        out += &line(row.iter().map(|c| &c[..]).collect());
+    }
+    out
+}
+
+/// Read the API key from a config file of `key = value` lines.
+fn read_config(path: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(path).ok()?;
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() == "api_key" {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+fn default_config() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("rustoa").join("config"))
+}
+
+fn match_rows(matches: &[Match]) -> Vec<Vec<String>> {
+    let teams = |teams: &[u32]| {
+        teams
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    matches
+        .iter()
+        .map(|m| {
+            vec![
+                m.match_key.clone(),
+                m.match_name.clone(),
+                teams(&m.red_teams),
+                teams(&m.blue_teams),
+                m.red_score.to_string(),
+                m.blue_score.to_string(),
+            ]
+        })
+        .collect()
+}
+
+const MATCH_HEADERS: [&str; 6] = ["match", "name", "red", "blue", "red_score", "blue_score"];
+
+/// The key of the event a match was played at, such as `1920-TX-TRQ` for
+/// `1920-TX-TRQ-Q001-1`.
+fn match_event_key(match_key: &str) -> Result<&str, String> {
+    match match_key.rsplitn(3, '-').nth(2) {
+        Some(key) if !key.is_empty() => Ok(key),
+        _ => Err(format!("{} is not a match key", match_key)),
+    }
+}
+
+/// Look up a single match among the matches of its event.
+fn find_match(client: &Client, match_key: &str) -> Result<Match, Box<dyn Error>> {
+    let event_key = match_event_key(match_key)?;
+    let matches = client.backend().matches(client, event_key)?;
+    match matches.into_iter().find(|m| m.match_key == match_key) {
+        Some(m) => Ok(m),
+        None => Err(format!("Match {} was not found", match_key).into()),
+    }
+}
+
+fn run(cli: Cli) -> Result<String, Box<dyn Error>> {
+    let config = cli.config.or_else(default_config);
+    let key = cli
+        .api_key
+        .or_else(|| read_config(config.as_ref()?))
+        .ok_or(
+            "No API key: pass --api-key, set TOA_API_KEY or add `api_key = ...` to the config file",
+        )?;
+    let client = Client::new(&key).with_cache(CachePolicy::new(Duration::from_secs(60)));
+
+    let output = match cli.command {
+        Command::Version => {
+            let mut map = HashMap::new();
+            map.insert("rustoa".to_string(), env!("CARGO_PKG_VERSION").to_string());
+            let status: serde_json::Value = serde_json::from_str(&client.raw("/")?.body)?;
+            let api = status["version"]
+                .as_str()
+                .ok_or("The API did not return its version")?;
+            map.insert("api".to_string(), api.to_string());
+            Output::fields(map)
+        }
+        Command::Team { number, list: None } => {
+            Output::fields(client.team_checked(number)?.properties())
+        }
+        Command::Team {
+            number,
+            list: Some(TeamList::Events { season }),
+        } => {
+            let events = client
+                .team_checked(number)?
+                .event_list(Season::value_of(season));
+            let rows = events
+                .iter()
+                .map(|e| {
+                    vec![
+                        e.event_key.clone(),
+                        e.event_name.clone(),
+                        e.start_date.get(..10).unwrap_or(&e.start_date).to_string(),
+                    ]
+                })
+                .collect();
+            Output {
+                headers: vec!["event_key", "event_name", "start_date"],
+                rows,
+                json: serde_json::to_value(&events[..])?,
+            }
+        }
+        Command::Team {
+            number,
+            list: Some(TeamList::Results { season }),
+        } => {
+            let report = client
+                .team_checked(number)?
+                .season_report(Season::value_of(season));
+            let rows = report
+                .events
+                .iter()
+                .map(|e| {
+                    vec![
+                        e.event_key.clone(),
+                        e.ranking.rank.to_string(),
+                        format!("{}-{}-{}", e.ranking.wins, e.ranking.losses, e.ranking.ties),
+                        e.ranking
+                            .opr
+                            .map(|o| format!("{:.2}", o))
+                            .unwrap_or_default(),
+                        e.awards.join("; "),
+                    ]
+                })
+                .collect();
+            Output {
+                headers: vec!["event_key", "rank", "record", "opr", "awards"],
+                rows,
+                json: serde_json::to_value(&report)?,
+            }
+        }
+        Command::Event { key, list: None } => {
+            client.event_info(&key)?;
+            Output::fields(client.event(&key).properties())
+        }
+        Command::Event {
+            key,
+            list: Some(EventList::Rankings),
+        } => {
+            let rankings = client.backend().rankings(&client, &key)?;
+            let rows = rankings
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.rank.to_string(),
+                        r.team_number.to_string(),
+                        format!("{}-{}-{}", r.wins, r.losses, r.ties),
+                        r.qualifying_points.to_string(),
+                        r.ranking_points.to_string(),
+                        r.opr.map(|o| format!("{:.2}", o)).unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            Output {
+                headers: vec!["rank", "team", "record", "qp", "rp", "opr"],
+                rows,
+                json: serde_json::to_value(&rankings)?,
+            }
+        }
+        Command::Event {
+            key,
+            list: Some(EventList::Matches),
+        } => {
+            let matches = client.backend().matches(&client, &key)?;
+            Output {
+                headers: MATCH_HEADERS.to_vec(),
+                rows: match_rows(&matches),
+                json: serde_json::to_value(&matches)?,
+            }
+        }
+        Command::Match { key } => {
+            let m = find_match(&client, &key)?;
+            Output {
+                headers: MATCH_HEADERS.to_vec(),
+                rows: match_rows(std::slice::from_ref(&m)),
+                json: serde_json::to_value(&m)?,
+            }
+        }
+    };
+    output.render(cli.format)
+}
+
+fn main() {
+    match run(Cli::parse()) {
+        Ok(out) => print!("{}", out),
+        Err(e) => {
+            eprintln!("rustoa: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_event_key, read_config, table, Format, Output};
+    use std::collections::HashMap;
+
+    #[test]
+    fn renders_every_format() {
+        let mut map = HashMap::new();
+        map.insert("team_number".to_string(), "16405".to_string());
+        map.insert("team_name_short".to_string(), "Iron Panthers".to_string());
+        let output = Output::fields(map);
+        assert_eq!(
+            output.render(Format::Table).unwrap(),
+            "field            value\n\
+             ---------------  -------------\n\
+             team_name_short  Iron Panthers\n\
+             team_number      16405\n"
+        );
+        assert_eq!(
+            output.render(Format::Csv).unwrap(),
+            "field,value\nteam_name_short,Iron Panthers\nteam_number,16405\n"
+        );
+        assert!(output
+            .render(Format::Json)
+            .unwrap()
+            .contains("\"team_number\": \"16405\""));
+        assert_eq!(table(&["a"], &[]), "a\n-\n");
+    }
+
+    #[test]
+    fn reads_api_key_from_config() {
+        let path = std::env::temp_dir().join(format!("rustoa-config-{}", std::process::id()));
+        std::fs::write(&path, "# rustoa\napi_key = \"abc123\"\n").unwrap();
+        assert_eq!(read_config(&path), Some("abc123".to_string()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn finds_the_event_of_a_match() {
+        assert_eq!(match_event_key("1920-TX-TRQ-Q001-1"), Ok("1920-TX-TRQ"));
+        assert_eq!(match_event_key("1920-USTXTRQ-E001-1"), Ok("1920-USTXTRQ"));
+        assert!(match_event_key("Q001").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! In-memory caching of API responses.
+//!
+//! The cache is opt-in. Enable it with [`Client::with_cache`](../struct.Client.html#method.with_cache)
+//! and a [`CachePolicy`](struct.CachePolicy.html) describing how long each endpoint stays fresh.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Describes how long responses from each endpoint are kept.
+///
+/// Rules are matched against the request path (for example `/event/1920-TX-TRQ/rankings`)
+/// in the order they were added. Paths that match no rule use the default TTL.
+///
+/// ```
+/// use std::time::Duration;
+/// use rustoa::CachePolicy;
+///
+/// let policy = CachePolicy::new(Duration::from_secs(300))
+///     .ttl(r"/rankings$", Duration::from_secs(30))
+///     .ttl(r"/1819$", Duration::from_secs(24 * 60 * 60));
+/// assert_eq!(policy.ttl_for("/event/1920-TX-TRQ/rankings"), Duration::from_secs(30));
+/// assert_eq!(policy.ttl_for("/team/16405/results/1819"), Duration::from_secs(86400));
+/// assert_eq!(policy.ttl_for("/team/16405/"), Duration::from_secs(300));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CachePolicy {
+    default_ttl: Duration,
+    rules: Vec<(Regex, Duration)>,
+}
+
+impl CachePolicy {
+    /// Create a new policy where every endpoint is kept for `default_ttl`.
+    pub fn new(default_ttl: Duration) -> CachePolicy {
+        CachePolicy {
+            default_ttl,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Add a rule that keeps responses whose path matches `pattern` for `ttl`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - A regular expression matched against the request path.
+    /// * `ttl` - How long matching responses stay fresh.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `pattern` is not a valid regular expression.
+    pub fn ttl(mut self, pattern: &str, ttl: Duration) -> CachePolicy {
+        let re = match Regex::new(pattern) {
+            Ok(r) => r,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+        self.rules.push((re, ttl));
+        self
+    }
+
+    /// The TTL that applies to the given request path.
+    pub fn ttl_for(&self, path: &str) -> Duration {
+        for (re, ttl) in self.rules.iter() {
+            if re.is_match(path) {
+                return *ttl;
+            }
+        }
+        self.default_ttl
+    }
+}
+
+impl Default for CachePolicy {
+    /// Five minutes for most endpoints, thirty seconds for live rankings and matches.
+    fn default() -> CachePolicy {
+        CachePolicy::new(Duration::from_secs(5 * 60))
+            .ttl(r"^/event/[^/]+/(rankings|matches)", Duration::from_secs(30))
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    body: String,
+    fetched_at: SystemTime,
+    expires: Instant,
+}
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct ResponseCache {
+    policy: CachePolicy,
+    entries: HashMap<String, Entry>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(policy: CachePolicy) -> ResponseCache {
+        ResponseCache {
+            policy,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, path: &str) -> Option<(String, SystemTime)> {
+        let expired = match self.entries.get(path) {
+            Some(entry) if entry.expires > Instant::now() => {
+                return Some((entry.body.clone(), entry.fetched_at))
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(path);
+        }
+        None
+    }
+
+    pub(crate) fn insert(&mut self, path: &str, body: String, fetched_at: SystemTime) {
+        let ttl = self.policy.ttl_for(path);
+        if ttl == Duration::from_secs(0) {
+            return;
+        }
+        let expires = Instant::now() + ttl;
+        self.entries.insert(
+            path.to_string(),
+            Entry {
+                body,
+                fetched_at,
+                expires,
+            },
+        );
+    }
+
+    pub(crate) fn invalidate(&mut self, path: &str) {
+        self.entries.remove(path);
+    }
+
+    pub(crate) fn invalidate_matching(&mut self, pattern: &Regex) {
+        self.entries.retain(|path, _| !pattern.is_match(path));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachePolicy, ResponseCache};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = CachePolicy::new(Duration::from_secs(10))
+            .ttl(r"/rankings$", Duration::from_secs(1))
+            .ttl(r"^/event/", Duration::from_secs(100));
+        assert_eq!(
+            policy.ttl_for("/event/1920-TX-TRQ/rankings"),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            policy.ttl_for("/event/1920-TX-TRQ"),
+            Duration::from_secs(100)
+        );
+        assert_eq!(policy.ttl_for("/team/16405/wlt"), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn entries_expire_and_invalidate() {
+        let policy =
+            CachePolicy::new(Duration::from_secs(60)).ttl(r"^/live", Duration::from_secs(0));
+        let mut cache = ResponseCache::new(policy);
+        let now = SystemTime::now();
+        cache.insert("/team/16405/wlt", "[]".to_string(), now);
+        cache.insert("/live", "[]".to_string(), now);
+        assert_eq!(cache.get("/team/16405/wlt"), Some(("[]".to_string(), now)));
+        assert_eq!(cache.get("/live"), None);
+
+        cache.insert("/team/16405/", "{}".to_string(), now);
+        cache.invalidate_matching(&regex::Regex::new(r"^/team/16405/$").unwrap());
+        assert_eq!(cache.get("/team/16405/"), None);
+        assert!(cache.get("/team/16405/wlt").is_some());
+
+        cache.invalidate("/team/16405/wlt");
+        assert_eq!(cache.get("/team/16405/wlt"), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! Apache Arrow and Parquet export.
+//!
+//! This module is only available with the `arrow` feature. It turns matches, rankings and
+//! season results into Arrow record batches, which can be written to Parquet files and
+//! loaded with tools like Polars, pandas or DuckDB.
+//!
+//! ```no_run
+//! use rustoa::columnar;
+//!
+//! let client = rustoa::Client::new("api_key");
+//! let event = client.event("1920-TX-TRQ");
+//! let batch = columnar::matches_batch(&event.matches(), &event.match_details()).unwrap();
+//! let file = std::fs::File::create("matches.parquet").unwrap();
+//! columnar::write_parquet(file, &batch).unwrap();
+//! ```
+//!
+//! The columns of a batch only depend on the kind of data, so batches of rankings or results
+//! from different events can always be written to one file. Match batches also have a column
+//! for each value in the score breakdowns, so convert the matches of a whole season with
+//! [`matches_batches`](fn.matches_batches.html) to give every event the same columns.
+
+use crate::{Match, Ranking, SeasonReport};
+use arrow::array::{
+    Array, ArrayRef, BooleanArray, Float64Array, Int32Array, Int64Array, ListBuilder, StringArray,
+    StringBuilder, UInt32Array, UInt32Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// The columns of a batch, built up one at a time.
+#[derive(Default)]
+struct Columns {
+    fields: Vec<Field>,
+    arrays: Vec<ArrayRef>,
+}
+
+impl Columns {
+    fn push(&mut self, name: &str, nullable: bool, array: ArrayRef) {
+        self.fields
+            .push(Field::new(name, array.data_type().clone(), nullable));
+        self.arrays.push(array);
+    }
+
+    fn strings<'a>(&mut self, name: &str, values: impl Iterator<Item = &'a str>) {
+        let array: StringArray = values.map(Some).collect();
+        self.push(name, false, Arc::new(array));
+    }
+
+    fn optional_strings<'a>(&mut self, name: &str, values: impl Iterator<Item = Option<&'a str>>) {
+        let array: StringArray = values.collect();
+        self.push(name, true, Arc::new(array));
+    }
+
+    fn u32s(&mut self, name: &str, values: impl Iterator<Item = u32>) {
+        self.push(name, false, Arc::new(values.collect::<UInt32Array>()));
+    }
+
+    fn i32s(&mut self, name: &str, values: impl Iterator<Item = i32>) {
+        self.push(name, false, Arc::new(values.collect::<Int32Array>()));
+    }
+
+    fn f64s(&mut self, name: &str, values: impl Iterator<Item = f64>) {
+        self.push(name, false, Arc::new(values.collect::<Float64Array>()));
+    }
+
+    fn optional_f64s(&mut self, name: &str, values: impl Iterator<Item = Option<f64>>) {
+        self.push(name, true, Arc::new(values.collect::<Float64Array>()));
+    }
+
+    /// A score of each match, which is null for matches that have not been played.
+    fn scores(&mut self, name: &str, matches: &[Match], score: fn(&Match) -> i32) {
+        let values = matches
+            .iter()
+            .map(|m| Some(score(m)).filter(|_| m.is_played()));
+        self.push(name, true, Arc::new(values.collect::<Int32Array>()));
+    }
+
+    fn team_lists<'a>(&mut self, name: &str, values: impl Iterator<Item = &'a Vec<u32>>) {
+        let mut builder = ListBuilder::new(UInt32Builder::new());
+        for teams in values {
+            builder.values().append_slice(teams);
+            builder.append(true);
+        }
+        self.push(name, false, Arc::new(builder.finish()));
+    }
+
+    fn finish(self) -> Result<RecordBatch, ArrowError> {
+        RecordBatch::try_new(Arc::new(Schema::new(self.fields)), self.arrays)
+    }
+}
+
+/// Convert matches into a record batch, one row per match.
+///
+/// The columns are the fields of [`Match`](../struct.Match.html), with the teams of each
+/// alliance as lists. The season-specific breakdowns in `details` (as returned by
+/// [`Event::match_details`](../struct.Event.html#method.match_details)) are joined on
+/// `match_key` and flattened into one column per value, so that `red.auto_delivered_skystones`
+/// becomes `red_auto_delivered_skystones`. Matches without details get nulls in those columns.
+///
+/// # Arguments
+///
+/// * `matches` - The matches to convert.
+/// * `details` - The score breakdowns of the matches. Pass an empty slice to leave them out.
+pub fn matches_batch(matches: &[Match], details: &[Value]) -> Result<RecordBatch, ArrowError> {
+    let mut batches = matches_batches(&[(matches, details)])?;
+    Ok(batches.remove(0))
+}
+
+/// Convert the matches of several events into record batches with the same schema, one
+/// batch per event.
+///
+/// Each batch is made like [`matches_batch`](fn.matches_batch.html) makes it, except that
+/// every batch has the breakdown columns of every event, so they can be concatenated or
+/// written to one Parquet file with [`write_parquet_batches`](fn.write_parquet_batches.html).
+///
+/// # Arguments
+///
+/// * `events` - The matches and score breakdowns of each event.
+pub fn matches_batches(events: &[(&[Match], &[Value])]) -> Result<Vec<RecordBatch>, ArrowError> {
+    // Flatten each breakdown, then gather every column any match of any event has.
+    let mut flat: Vec<BTreeMap<&str, BTreeMap<String, Value>>> = Vec::new();
+    for (_, details) in events.iter() {
+        let mut by_match = BTreeMap::new();
+        for detail in details.iter() {
+            if let Some(key) = detail["match_key"].as_str() {
+                let mut values = BTreeMap::new();
+                flatten("", detail, &mut values);
+                by_match.insert(key, values);
+            }
+        }
+        flat.push(by_match);
+    }
+    let base = base_columns(&[]).fields;
+    let mut names: Vec<String> = Vec::new();
+    for values in flat.iter().flat_map(|f| f.values()) {
+        for name in values.keys() {
+            let taken = base.iter().any(|f| f.name() == name);
+            if !taken && name != "match_detail_key" && !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names.sort();
+    let types: Vec<DataType> = names
+        .iter()
+        .map(|name| {
+            let values = flat
+                .iter()
+                .flat_map(|f| f.values())
+                .filter_map(|v| v.get(name))
+                .filter(|v| !v.is_null());
+            json_type(values)
+        })
+        .collect();
+
+    let mut batches = Vec::new();
+    for ((matches, _), flat) in events.iter().zip(flat.iter()) {
+        let mut c = base_columns(matches);
+        for (name, data_type) in names.iter().zip(types.iter()) {
+            let values: Vec<Option<&Value>> = matches
+                .iter()
+                .map(|m| {
+                    flat.get(&m.match_key[..])
+                        .and_then(|v| v.get(name))
+                        .filter(|v| !v.is_null())
+                })
+                .collect();
+            c.push(name, true, json_column(&values, data_type));
+        }
+        batches.push(c.finish()?);
+    }
+    Ok(batches)
+}
+
+/// The columns of the fields of each match.
+fn base_columns(matches: &[Match]) -> Columns {
+    let mut c = Columns::default();
+    c.strings("match_key", matches.iter().map(|m| &m.match_key[..]));
+    c.strings("event_key", matches.iter().map(|m| &m.event_key[..]));
+    c.strings("match_name", matches.iter().map(|m| &m.match_name[..]));
+    c.u32s(
+        "tournament_level",
+        matches.iter().map(|m| m.tournament_level),
+    );
+    c.optional_strings(
+        "scheduled_time",
+        matches.iter().map(|m| m.scheduled_time.as_deref()),
+    );
+    c.optional_strings(
+        "match_start_time",
+        matches.iter().map(|m| m.match_start_time.as_deref()),
+    );
+    c.team_lists("red_teams", matches.iter().map(|m| &m.red_teams));
+    c.team_lists("blue_teams", matches.iter().map(|m| &m.blue_teams));
+    c.scores("red_score", matches, |m| m.red_score);
+    c.scores("blue_score", matches, |m| m.blue_score);
+    c.scores("red_penalty", matches, |m| m.red_penalty);
+    c.scores("blue_penalty", matches, |m| m.blue_penalty);
+    c.scores("red_auto_score", matches, |m| m.red_auto_score);
+    c.scores("blue_auto_score", matches, |m| m.blue_auto_score);
+    c.scores("red_tele_score", matches, |m| m.red_tele_score);
+    c.scores("blue_tele_score", matches, |m| m.blue_tele_score);
+    c.scores("red_end_score", matches, |m| m.red_end_score);
+    c.scores("blue_end_score", matches, |m| m.blue_end_score);
+    c
+}
+
+/// Convert qualification rankings into a record batch, one row per team and event.
+///
+/// The columns are the fields of [`Ranking`](../struct.Ranking.html).
+pub fn rankings_batch(rankings: &[Ranking]) -> Result<RecordBatch, ArrowError> {
+    let mut c = Columns::default();
+    c.strings("event_key", rankings.iter().map(|r| &r.event_key[..]));
+    c.u32s("team_number", rankings.iter().map(|r| r.team_number));
+    c.u32s("rank", rankings.iter().map(|r| r.rank));
+    c.i32s("rank_change", rankings.iter().map(|r| r.rank_change));
+    c.u32s("wins", rankings.iter().map(|r| r.wins));
+    c.u32s("losses", rankings.iter().map(|r| r.losses));
+    c.u32s("ties", rankings.iter().map(|r| r.ties));
+    c.u32s("played", rankings.iter().map(|r| r.played));
+    c.u32s("disqualified", rankings.iter().map(|r| r.disqualified));
+    c.optional_f64s("opr", rankings.iter().map(|r| r.opr));
+    c.optional_f64s("np_opr", rankings.iter().map(|r| r.np_opr));
+    c.optional_f64s(
+        "highest_qual_score",
+        rankings.iter().map(|r| r.highest_qual_score),
+    );
+    c.f64s("ranking_points", rankings.iter().map(|r| r.ranking_points));
+    c.f64s(
+        "qualifying_points",
+        rankings.iter().map(|r| r.qualifying_points),
+    );
+    c.f64s(
+        "tiebreaker_points",
+        rankings.iter().map(|r| r.tiebreaker_points),
+    );
+    c.finish()
+}
+
+/// Convert the season results of one or more teams into a record batch, one row per team
+/// and event.
+pub fn results_batch(reports: &[SeasonReport]) -> Result<RecordBatch, ArrowError> {
+    let rows: Vec<_> = reports
+        .iter()
+        .flat_map(|report| report.events.iter().map(move |e| (report, e)))
+        .collect();
+    let mut c = Columns::default();
+    c.u32s("team_number", rows.iter().map(|(r, _)| r.team_number));
+    c.i32s("season", rows.iter().map(|(r, _)| r.season));
+    c.strings("event_key", rows.iter().map(|(_, e)| &e.event_key[..]));
+    c.strings("event_name", rows.iter().map(|(_, e)| &e.event_name[..]));
+    c.u32s("rank", rows.iter().map(|(_, e)| e.ranking.rank));
+    c.u32s("wins", rows.iter().map(|(_, e)| e.ranking.wins));
+    c.u32s("losses", rows.iter().map(|(_, e)| e.ranking.losses));
+    c.u32s("ties", rows.iter().map(|(_, e)| e.ranking.ties));
+    c.u32s("played", rows.iter().map(|(_, e)| e.ranking.played));
+    c.optional_f64s("opr", rows.iter().map(|(_, e)| e.ranking.opr));
+    c.optional_f64s("np_opr", rows.iter().map(|(_, e)| e.ranking.np_opr));
+    c.optional_f64s(
+        "highest_qual_score",
+        rows.iter().map(|(_, e)| e.ranking.highest_qual_score),
+    );
+    let mut awards = ListBuilder::new(StringBuilder::new());
+    for (_, e) in rows.iter() {
+        for award in e.awards.iter() {
+            awards.values().append_value(award);
+        }
+        awards.append(true);
+    }
+    c.push("awards", false, Arc::new(awards.finish()));
+    c.optional_strings(
+        "advanced_to",
+        rows.iter().map(|(_, e)| e.advanced_to.as_deref()),
+    );
+    c.finish()
+}
+
+/// Write a record batch to a Parquet file.
+pub fn write_parquet<W: Write + Send>(out: W, batch: &RecordBatch) -> parquet::errors::Result<()> {
+    write_parquet_batches(out, std::slice::from_ref(batch))
+}
+
+/// Write record batches with the same schema, such as those made by
+/// [`matches_batches`](fn.matches_batches.html), to one Parquet file.
+///
+/// Nothing is written if there are no batches.
+pub fn write_parquet_batches<W: Write + Send>(
+    out: W,
+    batches: &[RecordBatch],
+) -> parquet::errors::Result<()> {
+    let schema = match batches.first() {
+        Some(b) => b.schema(),
+        None => return Ok(()),
+    };
+    let mut writer = ArrowWriter::try_new(out, schema, None)?;
+    for batch in batches.iter() {
+        writer.write(batch)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter() {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}_{}", prefix, key)
+                };
+                flatten(&name, value, out);
+            }
+        }
+        value => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// The narrowest type that holds all of the values. Columns without any values hold strings.
+fn json_type<'a>(values: impl Iterator<Item = &'a Value> + Clone) -> DataType {
+    let mut present = values.peekable();
+    if present.peek().is_none() {
+        DataType::Utf8
+    } else if present.clone().all(|v| v.is_boolean()) {
+        DataType::Boolean
+    } else if present.clone().all(|v| v.is_i64()) {
+        DataType::Int64
+    } else if present.all(|v| v.is_number()) {
+        DataType::Float64
+    } else {
+        DataType::Utf8
+    }
+}
+
+/// Build a column of the given type from JSON values.
+fn json_column(values: &[Option<&Value>], data_type: &DataType) -> ArrayRef {
+    match data_type {
+        DataType::Boolean => {
+            let array: BooleanArray = values.iter().map(|v| v.and_then(|v| v.as_bool())).collect();
+            Arc::new(array)
+        }
+        DataType::Int64 => {
+            let array: Int64Array = values.iter().map(|v| v.and_then(|v| v.as_i64())).collect();
+            Arc::new(array)
+        }
+        DataType::Float64 => {
+            let array: Float64Array = values.iter().map(|v| v.and_then(|v| v.as_f64())).collect();
+            Arc::new(array)
+        }
+        _ => {
+            let array: StringArray = values
+                .iter()
+                .map(|v| {
+                    v.map(|v| match v.as_str() {
+                        Some(s) => s.to_string(),
+                        None => v.to_string(),
+                    })
+                })
+                .collect();
+            Arc::new(array)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_batch, matches_batches, write_parquet, write_parquet_batches};
+    use crate::Match;
+    use arrow::array::{Array, Int64Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn flattens_details_and_round_trips_through_parquet() {
+        let matches: Vec<Match> = ["Q001", "Q002"]
+            .iter()
+            .map(|q| Match {
+                match_key: format!("1920-TX-TRQ-{}-1", q),
+                red_teams: vec![17010, 8565],
+                blue_teams: vec![16405, 12456],
+                ..Default::default()
+            })
+            .collect();
+        let details = vec![serde_json::json!({
+            "match_detail_key": "1920-TX-TRQ-Q001-1-DTL",
+            "match_key": "1920-TX-TRQ-Q001-1",
+            "red": {"auto_delivered_skystones": 2, "robot_1": {"parked": "YES"}},
+            "blue": {"auto_delivered_skystones": 1}
+        })];
+        let batch = matches_batch(&matches, &details).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let skystones = batch
+            .column_by_name("red_auto_delivered_skystones")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(skystones.value(0), 2);
+        assert!(skystones.is_null(1));
+        let parked = batch.column_by_name("red_robot_1_parked").unwrap();
+        assert_eq!(
+            parked
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(0),
+            "YES"
+        );
+        assert!(batch.column_by_name("match_detail_key").is_none());
+
+        let path = std::env::temp_dir().join(format!("rustoa-{}.parquet", std::process::id()));
+        write_parquet(std::fs::File::create(&path).unwrap(), &batch).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let read: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(read[0], batch);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn events_share_one_schema() {
+        let quals = |key: &str, start: Option<&str>| Match {
+            match_key: key.to_string(),
+            match_start_time: start.map(|s| s.to_string()),
+            ..Default::default()
+        };
+        let trq = vec![quals(
+            "1920-TX-TRQ-Q001-1",
+            Some("2019-12-07T09:00:00.000Z"),
+        )];
+        let plq = vec![quals("1920-TX-PLQ-Q001-1", None)];
+        let details = [serde_json::json!({
+            "match_key": "1920-TX-TRQ-Q001-1",
+            "red": {"auto_delivered_skystones": 2}
+        })];
+        let batches = matches_batches(&[(&trq[..], &details[..]), (&plq[..], &[])]).unwrap();
+        assert_eq!(batches[0].schema(), batches[1].schema());
+        assert!(batches[1]
+            .column_by_name("red_auto_delivered_skystones")
+            .unwrap()
+            .is_null(0));
+        assert_eq!(
+            matches_batch(&plq, &[]).unwrap().schema(),
+            matches_batch(&trq, &[]).unwrap().schema()
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("rustoa-season-{}.parquet", std::process::id()));
+        write_parquet_batches(std::fs::File::create(&path).unwrap(), &batches).unwrap();
+        let rows: usize =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap()
+                .map(|b| b.unwrap().num_rows())
+                .sum();
+        assert_eq!(rows, 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! Persistent on-disk caching of API responses.
+//!
+//! Every successful response is written to a directory as a small JSON file holding the
+//! request path, the raw body and the time it was fetched. When The Orange Alliance cannot
+//! be reached the last stored response is served instead and marked as stale.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Numbers the temporary files, so that writers of the same path never share one.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of stored API responses.
+///
+/// Attach it to a client with [`Client::with_disk_cache`](../struct.Client.html#method.with_disk_cache).
+///
+/// ```no_run
+/// let cache = rustoa::DiskCache::open("toa-cache").unwrap();
+/// let client = rustoa::Client::new("api_key").with_disk_cache(cache);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_age: Duration,
+}
+
+/// A response read from the disk cache.
+#[derive(Clone, Debug)]
+pub struct StoredResponse {
+    /// The request path, such as `/event/1920-TX-TRQ/rankings`.
+    pub path: String,
+    /// The raw response body.
+    pub body: String,
+    /// When the response was fetched from the API.
+    pub fetched_at: SystemTime,
+}
+
+impl DiskCache {
+    /// Open (and create if needed) a disk cache in the given directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the responses are stored in.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<DiskCache> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(DiskCache {
+            dir,
+            max_age: Duration::from_secs(0),
+        })
+    }
+
+    /// Serve stored responses younger than `max_age` without contacting the API.
+    ///
+    /// By default the API is always tried first and stored responses are only used when it
+    /// cannot be reached.
+    pub fn max_age(mut self, max_age: Duration) -> DiskCache {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The directory the responses are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn file_for(&self, path: &str) -> PathBuf {
+        let mut name = String::new();
+        for b in path.bytes() {
+            if b.is_ascii_alphanumeric() || b == b'-' {
+                name.push(b as char);
+            } else {
+                name.push_str(&format!("_{:02x}", b));
+            }
+        }
+        name.push_str(".json");
+        self.dir.join(name)
+    }
+
+    /// Read the stored response for a request path, if there is one.
+    pub fn get(&self, path: &str) -> Option<StoredResponse> {
+        let text = fs::read_to_string(self.file_for(path)).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&text).ok()?;
+        let body = json["body"].as_str()?.to_string();
+        let secs = json["fetched_at"].as_u64()?;
+        Some(StoredResponse {
+            path: path.to_string(),
+            body,
+            fetched_at: UNIX_EPOCH + Duration::from_secs(secs),
+        })
+    }
+
+    /// Read the stored response for a request path if it is younger than the configured
+    /// maximum age.
+    pub(crate) fn get_fresh(&self, path: &str) -> Option<StoredResponse> {
+        if self.max_age == Duration::from_secs(0) {
+            return None;
+        }
+        let stored = self.get(path)?;
+        match stored.fetched_at.elapsed() {
+            Ok(age) if age <= self.max_age => Some(stored),
+            _ => None,
+        }
+    }
+
+    /// Store a response body for a request path, stamped with the current time.
+    pub fn put(&self, path: &str, body: &str) -> io::Result<()> {
+        let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs(),
+            Err(_) => 0,
+        };
+        let json = serde_json::json!({
+            "path": path,
+            "fetched_at": secs,
+            "body": body,
+        });
+        let file = self.file_for(path);
+        let tmp = file.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, json.to_string())?;
+        fs::rename(&tmp, &file)
+    }
+
+    /// Remove the stored response for a request path.
+    pub fn remove(&self, path: &str) -> io::Result<()> {
+        match fs::remove_file(self.file_for(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiskCache;
+    use std::time::Duration;
+
+    #[test]
+    fn stores_and_reads_responses() {
+        let dir = std::env::temp_dir().join(format!("rustoa-disk-cache-{}", std::process::id()));
+        let cache = DiskCache::open(&dir).unwrap();
+        cache
+            .put("/team/16405/", "[{\"team_number\": 16405}]")
+            .unwrap();
+        cache.put("/team/16405", "[]").unwrap();
+
+        let stored = cache.get("/team/16405/").unwrap();
+        assert_eq!(stored.body, "[{\"team_number\": 16405}]");
+        assert_eq!(cache.get("/team/16405").unwrap().body, "[]");
+        assert!(cache.get_fresh("/team/16405/").is_none());
+        assert!(cache
+            .clone()
+            .max_age(Duration::from_secs(60))
+            .get_fresh("/team/16405/")
+            .is_some());
+
+        cache.remove("/team/16405/").unwrap();
+        assert!(cache.get("/team/16405/").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writers_do_not_share_a_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("rustoa-disk-race-{}", std::process::id()));
+        let cache = DiskCache::open(&dir).unwrap();
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..20 {
+                        cache.put("/team/16405", "[]").unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(cache.get("/team/16405").unwrap().body, "[]");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+//! The events a team attended.
+
+use std::ops::Deref;
+
+/// The details of an event a team attended.
+///
+/// You can get these with [`Team::event_list`](struct.Team.html#method.event_list), and an
+/// [`Event`](struct.Event.html) for the same key with
+/// [`Client::event`](struct.Client.html#method.event).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventSummary {
+    /// The key of the event, such as `1920-TX-TRQ`.
+    pub event_key: String,
+    pub event_name: String,
+    /// The first day of the event, such as `2019-12-07T00:00:00.000Z`.
+    pub start_date: String,
+    pub end_date: String,
+    pub region_key: Option<String>,
+    /// The kind of event, such as `QUAL` or `CMP`.
+    pub event_type_key: Option<String>,
+    pub city: Option<String>,
+    pub state_prov: Option<String>,
+    pub country: Option<String>,
+    /// The key of the event teams advance to from this one, such as `1920-TX-NTXC`.
+    pub advance_event: Option<String>,
+}
+
+impl EventSummary {
+    pub(crate) fn from_json(json: &serde_json::Value) -> Option<EventSummary> {
+        let text = |key: &str| json[key].as_str().map(|s| s.to_string());
+        Some(EventSummary {
+            event_key: text("event_key")?,
+            event_name: text("event_name")?,
+            start_date: text("start_date").unwrap_or_default(),
+            end_date: text("end_date").unwrap_or_default(),
+            region_key: text("region_key"),
+            event_type_key: text("event_type_key"),
+            city: text("city"),
+            state_prov: text("state_prov"),
+            country: text("country"),
+            advance_event: text("advance_event"),
+        })
+    }
+
+    /// The name of the event in lowercase with underscores, such as
+    /// `trinity_river_qualifier`.
+    pub fn slug(&self) -> String {
+        self.event_name.replace(" ", "_").to_lowercase()
+    }
+}
+
+/// The details of an event, as returned by any [`Backend`](trait.Backend.html).
+pub type EventInfo = EventSummary;
+
+/// The events a team attended in a season, in the order they were played.
+///
+/// It dereferences to a slice of [`EventSummary`](struct.EventSummary.html), and can be
+/// searched by key, name or slug.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventList {
+    events: Vec<EventSummary>,
+}
+
+impl EventList {
+    /// Sort the events by date, then by key.
+    pub(crate) fn new(mut events: Vec<EventSummary>) -> EventList {
+        events.sort_by(|a, b| (&a.start_date, &a.event_key).cmp(&(&b.start_date, &b.event_key)));
+        EventList { events }
+    }
+
+    /// The event with this key, such as `1920-TX-TRQ`.
+    pub fn get(&self, event_key: &str) -> Option<&EventSummary> {
+        self.events.iter().find(|e| e.event_key == event_key)
+    }
+
+    /// The events with this name, ignoring case. Most names are unique within a season,
+    /// but divisions and events held at the same venue can share one.
+    pub fn find_by_name(&self, name: &str) -> Vec<&EventSummary> {
+        self.events
+            .iter()
+            .filter(|e| e.event_name.eq_ignore_ascii_case(name.trim()))
+            .collect()
+    }
+
+    /// The events with this slug, such as `trinity_river_qualifier`.
+    pub fn find_by_slug(&self, slug: &str) -> Vec<&EventSummary> {
+        self.events.iter().filter(|e| e.slug() == slug).collect()
+    }
+
+    /// The keys of the events, in order.
+    pub fn keys(&self) -> Vec<&str> {
+        self.events.iter().map(|e| &e.event_key[..]).collect()
+    }
+
+    pub fn into_vec(self) -> Vec<EventSummary> {
+        self.events
+    }
+}
+
+impl Deref for EventList {
+    type Target = [EventSummary];
+
+    fn deref(&self) -> &[EventSummary] {
+        &self.events
+    }
+}
+
+impl IntoIterator for EventList {
+    type Item = EventSummary;
+    type IntoIter = std::vec::IntoIter<EventSummary>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a EventList {
+    type Item = &'a EventSummary;
+    type IntoIter = std::slice::Iter<'a, EventSummary>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
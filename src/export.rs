@@ -0,0 +1,295 @@
+//! CSV export of rankings, matches, season results and awards.
+//!
+//! This module is only available with the `csv` feature. Every table has a fixed set of
+//! columns, listed in the constants of this module, and always starts with a header row.
+//!
+//! ```no_run
+//! use rustoa::export::Exporter;
+//!
+//! let client = rustoa::Client::new("api_key");
+//! let event = client.event("1920-TX-TRQ");
+//! let file = std::fs::File::create("rankings.csv").unwrap();
+//! Exporter::new().write_rankings(file, &event.rankings()).unwrap();
+//! ```
+
+use crate::{Match, Ranking, SeasonReport};
+use std::io::Write;
+
+/// The columns of [`Exporter::write_rankings`](struct.Exporter.html#method.write_rankings).
+pub const RANKING_COLUMNS: [&str; 15] = [
+    "event_key",
+    "rank",
+    "team_number",
+    "wins",
+    "losses",
+    "ties",
+    "played",
+    "disqualified",
+    "ranking_points",
+    "qualifying_points",
+    "tiebreaker_points",
+    "highest_qual_score",
+    "opr",
+    "np_opr",
+    "rank_change",
+];
+
+/// The columns of [`Exporter::write_matches`](struct.Exporter.html#method.write_matches).
+///
+/// Teams are separated by spaces, and `played` is `true` or `false`.
+pub const MATCH_COLUMNS: [&str; 19] = [
+    "match_key",
+    "event_key",
+    "match_name",
+    "tournament_level",
+    "scheduled_time",
+    "played",
+    "red_teams",
+    "blue_teams",
+    "red_score",
+    "blue_score",
+    "red_auto_score",
+    "blue_auto_score",
+    "red_tele_score",
+    "blue_tele_score",
+    "red_end_score",
+    "blue_end_score",
+    "red_penalty",
+    "blue_penalty",
+    "winner",
+];
+
+/// The columns of [`Exporter::write_results`](struct.Exporter.html#method.write_results).
+///
+/// Awards are separated by semicolons.
+pub const RESULT_COLUMNS: [&str; 14] = [
+    "team_number",
+    "season",
+    "event_key",
+    "event_name",
+    "rank",
+    "wins",
+    "losses",
+    "ties",
+    "played",
+    "opr",
+    "np_opr",
+    "highest_qual_score",
+    "awards",
+    "advanced_to",
+];
+
+/// The columns of [`Exporter::write_awards`](struct.Exporter.html#method.write_awards).
+pub const AWARD_COLUMNS: [&str; 5] = ["team_number", "season", "event_key", "event_name", "award"];
+
+/// Writes tables as CSV.
+#[derive(Clone, Debug)]
+pub struct Exporter {
+    delimiter: u8,
+}
+
+/// A value that may not have been computed yet, which is left empty.
+fn optional(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+impl Exporter {
+    /// Create an exporter that separates fields with commas.
+    pub fn new() -> Exporter {
+        Exporter { delimiter: b',' }
+    }
+
+    /// The byte fields are separated with, such as `b';'` for spreadsheets in locales that
+    /// use a comma as the decimal separator, or `b'\t'` for tab-separated values.
+    pub fn delimiter(mut self, delimiter: u8) -> Exporter {
+        self.delimiter = delimiter;
+        self
+    }
+
+    fn writer<W: Write>(&self, out: W, columns: &[&str]) -> csv::Result<csv::Writer<W>> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(out);
+        writer.write_record(columns)?;
+        Ok(writer)
+    }
+
+    /// Write the qualification rankings of an event, one row per team.
+    pub fn write_rankings<W: Write>(&self, out: W, rankings: &[Ranking]) -> csv::Result<()> {
+        let mut writer = self.writer(out, &RANKING_COLUMNS)?;
+        for r in rankings.iter() {
+            writer.write_record([
+                r.event_key.clone(),
+                r.rank.to_string(),
+                r.team_number.to_string(),
+                r.wins.to_string(),
+                r.losses.to_string(),
+                r.ties.to_string(),
+                r.played.to_string(),
+                r.disqualified.to_string(),
+                r.ranking_points.to_string(),
+                r.qualifying_points.to_string(),
+                r.tiebreaker_points.to_string(),
+                optional(r.highest_qual_score),
+                optional(r.opr),
+                optional(r.np_opr),
+                r.rank_change.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write a list of matches with their score breakdowns, one row per match.
+    pub fn write_matches<W: Write>(&self, out: W, matches: &[Match]) -> csv::Result<()> {
+        let teams = |teams: &[u32]| {
+            teams
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mut writer = self.writer(out, &MATCH_COLUMNS)?;
+        for m in matches.iter() {
+            // Matches that have not been played have no scores yet.
+            let score = |s: i32| {
+                if m.is_played() {
+                    s.to_string()
+                } else {
+                    String::new()
+                }
+            };
+            let winner = if !m.is_played() {
+                ""
+            } else if m.red_score > m.blue_score {
+                "red"
+            } else if m.red_score < m.blue_score {
+                "blue"
+            } else {
+                "tie"
+            };
+            writer.write_record([
+                m.match_key.clone(),
+                m.event_key.clone(),
+                m.match_name.clone(),
+                m.tournament_level.to_string(),
+                m.scheduled_time.clone().unwrap_or_default(),
+                m.is_played().to_string(),
+                teams(&m.red_teams),
+                teams(&m.blue_teams),
+                score(m.red_score),
+                score(m.blue_score),
+                score(m.red_auto_score),
+                score(m.blue_auto_score),
+                score(m.red_tele_score),
+                score(m.blue_tele_score),
+                score(m.red_end_score),
+                score(m.blue_end_score),
+                score(m.red_penalty),
+                score(m.blue_penalty),
+                winner.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write a team's season results, one row per event.
+    pub fn write_results<W: Write>(&self, out: W, report: &SeasonReport) -> csv::Result<()> {
+        let mut writer = self.writer(out, &RESULT_COLUMNS)?;
+        for event in report.events.iter() {
+            let r = &event.ranking;
+            writer.write_record([
+                report.team_number.to_string(),
+                report.season.to_string(),
+                event.event_key.clone(),
+                event.event_name.clone(),
+                r.rank.to_string(),
+                r.wins.to_string(),
+                r.losses.to_string(),
+                r.ties.to_string(),
+                r.played.to_string(),
+                optional(r.opr),
+                optional(r.np_opr),
+                optional(r.highest_qual_score),
+                event.awards.join("; "),
+                event.advanced_to.clone().unwrap_or_default(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the awards a team won during a season, one row per award.
+    pub fn write_awards<W: Write>(&self, out: W, report: &SeasonReport) -> csv::Result<()> {
+        let mut writer = self.writer(out, &AWARD_COLUMNS)?;
+        for event in report.events.iter() {
+            for award in event.awards.iter() {
+                writer.write_record([
+                    &report.team_number.to_string()[..],
+                    &report.season.to_string()[..],
+                    &event.event_key[..],
+                    &event.event_name[..],
+                    &award[..],
+                ])?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for Exporter {
+    fn default() -> Exporter {
+        Exporter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Exporter;
+    use crate::{EventResult, Match, Ranking, SeasonReport};
+
+    #[test]
+    fn writes_headers_and_rows() {
+        let m = Match {
+            match_key: "1920-TX-TRQ-Q001-1".to_string(),
+            match_name: "Quals 1".to_string(),
+            match_start_time: Some("2019-12-07T15:00:00.000Z".to_string()),
+            red_teams: vec![17010, 8565],
+            blue_teams: vec![16405, 12456],
+            red_score: 107,
+            blue_score: 108,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        Exporter::new()
+            .delimiter(b';')
+            .write_matches(&mut out, &[m])
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("match_key;event_key;match_name;"));
+        assert!(lines[1].starts_with("1920-TX-TRQ-Q001-1;;Quals 1;0;;true;17010 8565;"));
+        assert!(lines[1].ends_with(";blue"));
+
+        let report = SeasonReport {
+            team_number: 16405,
+            season: 1920,
+            events: vec![EventResult {
+                event_key: "1920-TX-PLQ".to_string(),
+                event_name: "Plano, Qualifier".to_string(),
+                ranking: Ranking::default(),
+                awards: vec!["Think Award Winner".to_string()],
+                advanced_to: None,
+            }],
+        };
+        let mut out = Vec::new();
+        Exporter::new().write_awards(&mut out, &report).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "team_number,season,event_key,event_name,award\n\
+             16405,1920,1920-TX-PLQ,\"Plano, Qualifier\",Think Award Winner\n"
+        );
+    }
+}
@@ -0,0 +1,529 @@
+//! The FTCScout GraphQL backend.
+
+use crate::backend::{
+    event_code, parse, unexpected, year, Backend, BackendError, QuickStats, RankedStat, TeamInfo,
+};
+use crate::matches::Round;
+use crate::{Client, EventInfo, Match, Ranking, TransportRequest};
+use serde_json::{json, Value};
+
+/// The seasons whose event stats and match scores have their own GraphQL types. The
+/// 2020-2021 and 2021-2022 seasons also had remote events, which have no alliances.
+const SEASON_TYPES: [&str; 6] = ["2019", "2020Trad", "2021Trad", "2022", "2023", "2024"];
+
+/// Select the same fields from every season's type of a union, such as `TeamEventStats`.
+fn on_every_season(union: &str, fields: &str) -> String {
+    SEASON_TYPES
+        .iter()
+        .map(|season| format!("... on {}{} {{ {} }}", union, season, fields))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn stats_fields() -> String {
+    on_every_season(
+        "TeamEventStats",
+        "rank rp tb1 wins losses ties dq qualMatchesPlayed opr { totalPoints totalPointsNp }",
+    )
+}
+
+fn scores_fields() -> String {
+    let alliance = "totalPoints autoPoints dcPoints egPoints penaltyPointsCommitted";
+    on_every_season(
+        "MatchScores",
+        &format!("red {{ {} }} blue {{ {} }}", alliance, alliance),
+    )
+}
+
+/// [FTCScout](https://ftcscout.org)'s GraphQL API, which covers the 2019-2020 season onwards.
+///
+/// It needs no API key. Event keys are the season followed by FIRST's event code, as with
+/// [`FtcEvents`](struct.FtcEvents.html), such as `2223-USTXDAQ`, and match keys and
+/// tournament levels are made up the same way as TOA's.
+///
+/// ```no_run
+/// use rustoa::backend::FtcScout;
+/// use rustoa::Season;
+///
+/// let client = rustoa::Client::new("").with_backend(FtcScout::new());
+/// let opr = client.team(16405).opr(Season::PowerPlay);
+/// let rank = client.event("2223-USTXDAQ").rank(16405);
+/// let stats = client.quick_stats(16405, Season::PowerPlay).unwrap();
+/// ```
+///
+/// Match scheduled times, and the highest qualification score and qualifying points of a
+/// [`Ranking`](../struct.Ranking.html), are not available from FTCScout.
+#[derive(Clone, Debug)]
+pub struct FtcScout {
+    base_url: String,
+}
+
+impl FtcScout {
+    pub fn new() -> FtcScout {
+        FtcScout {
+            base_url: "https://api.ftcscout.org".to_string(),
+        }
+    }
+
+    /// Send requests to a different server, such as a local stand-in in tests.
+    pub fn with_base_url(mut self, base_url: &str) -> FtcScout {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Run a GraphQL query and return its `data`.
+    fn query(
+        &self,
+        client: &Client,
+        operation: &str,
+        query: &str,
+        variables: Value,
+    ) -> Result<Value, BackendError> {
+        let request = TransportRequest {
+            url: format!("{}/graphql", self.base_url),
+            path: "/graphql".to_string(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        };
+        let body = json!({
+            "operationName": operation,
+            "query": query,
+            "variables": variables,
+        });
+        let resp = client
+            .send_post(&request, &body.to_string())
+            .map_err(|e| BackendError::Transport(e.to_string()))?;
+        if !resp.is_success() {
+            return Err(BackendError::Api(resp.status));
+        }
+        let mut json = parse(&resp.body)?;
+        if let Some(errors) = json["errors"].as_array().filter(|e| !e.is_empty()) {
+            let messages: Vec<&str> = errors
+                .iter()
+                .filter_map(|e| e["message"].as_str())
+                .collect();
+            return Err(BackendError::Query(messages.join("; ")));
+        }
+        Ok(json["data"].take())
+    }
+
+    fn event_query(
+        &self,
+        client: &Client,
+        operation: &str,
+        fields: &str,
+        event_key: &str,
+    ) -> Result<Value, BackendError> {
+        let (season, code) = event_code(event_key)?;
+        let query = format!(
+            "query {}($season: Int!, $code: String!) {{ eventByCode(season: $season, code: $code) {{ {} }} }}",
+            operation, fields
+        );
+        let data = self.query(
+            client,
+            operation,
+            &query,
+            json!({ "season": season, "code": code }),
+        )?;
+        match &data["eventByCode"] {
+            Value::Null => Err(BackendError::NotFound(event_key.to_string())),
+            event => Ok(event.clone()),
+        }
+    }
+
+    fn team_query(
+        &self,
+        client: &Client,
+        operation: &str,
+        fields: &str,
+        team_number: u32,
+        season: Option<i32>,
+    ) -> Result<Value, BackendError> {
+        let (params, variables) = match season {
+            Some(s) => (
+                "$number: Int!, $season: Int!",
+                json!({ "number": team_number, "season": year(s) }),
+            ),
+            None => ("$number: Int!", json!({ "number": team_number })),
+        };
+        let query = format!(
+            "query {}({}) {{ teamByNumber(number: $number) {{ {} }} }}",
+            operation, params, fields
+        );
+        let data = self.query(client, operation, &query, variables)?;
+        match &data["teamByNumber"] {
+            Value::Null => Err(BackendError::NotFound(format!("Team {}", team_number))),
+            team => Ok(team.clone()),
+        }
+    }
+}
+
+impl Default for FtcScout {
+    fn default() -> FtcScout {
+        FtcScout::new()
+    }
+}
+
+/// A team's ranking from its event stats, or `None` if it has not played yet.
+fn ranking(team_number: u32, event_key: &str, stats: &Value) -> Option<Ranking> {
+    let count = |key: &str| stats[key].as_u64().map(|n| n as u32);
+    Some(Ranking {
+        team_number,
+        event_key: event_key.to_string(),
+        rank: count("rank")?,
+        wins: count("wins")?,
+        losses: count("losses").unwrap_or(0),
+        ties: count("ties").unwrap_or(0),
+        played: count("qualMatchesPlayed").unwrap_or(0),
+        disqualified: count("dq").unwrap_or(0),
+        opr: stats["opr"]["totalPoints"].as_f64(),
+        np_opr: stats["opr"]["totalPointsNp"].as_f64(),
+        ranking_points: stats["rp"].as_f64().unwrap_or(0.0),
+        tiebreaker_points: stats["tb1"].as_f64().unwrap_or(0.0),
+        ..Default::default()
+    })
+}
+
+fn ranked_stat(json: &Value) -> RankedStat {
+    RankedStat {
+        value: json["value"].as_f64().unwrap_or(0.0),
+        rank: json["rank"].as_u64().map(|r| r as u32),
+    }
+}
+
+impl Backend for FtcScout {
+    fn team(&self, client: &Client, team_number: u32) -> Result<TeamInfo, BackendError> {
+        let team = self.team_query(
+            client,
+            "Team",
+            "number name schoolName rookieYear website location { city state country }",
+            team_number,
+            None,
+        )?;
+        let text = |json: &Value| json.as_str().map(|s| s.to_string());
+        Ok(TeamInfo {
+            team_number,
+            name_short: text(&team["name"]).unwrap_or_default(),
+            name_full: text(&team["schoolName"]),
+            city: text(&team["location"]["city"]),
+            state_prov: text(&team["location"]["state"]),
+            country: text(&team["location"]["country"]),
+            rookie_year: team["rookieYear"].as_u64().map(|y| y as u32),
+            website: text(&team["website"]).filter(|w| !w.is_empty()),
+        })
+    }
+
+    fn event(&self, client: &Client, event_key: &str) -> Result<EventInfo, BackendError> {
+        let event = self.event_query(
+            client,
+            "Event",
+            "name start end type regionCode location { city state country }",
+            event_key,
+        )?;
+        let text = |json: &Value| json.as_str().map(|s| s.to_string());
+        Ok(EventInfo {
+            event_key: event_key.to_string(),
+            event_name: text(&event["name"]).ok_or_else(|| unexpected("event"))?,
+            start_date: text(&event["start"]).unwrap_or_default(),
+            end_date: text(&event["end"]).unwrap_or_default(),
+            region_key: text(&event["regionCode"]),
+            event_type_key: text(&event["type"]),
+            city: text(&event["location"]["city"]),
+            state_prov: text(&event["location"]["state"]),
+            country: text(&event["location"]["country"]),
+            advance_event: None,
+        })
+    }
+
+    fn rankings(&self, client: &Client, event_key: &str) -> Result<Vec<Ranking>, BackendError> {
+        let fields = format!("teams {{ teamNumber stats {{ {} }} }}", stats_fields());
+        let event = self.event_query(client, "Rankings", &fields, event_key)?;
+        let teams = event["teams"]
+            .as_array()
+            .ok_or_else(|| unexpected("rankings"))?;
+        let mut rankings: Vec<Ranking> = teams
+            .iter()
+            .filter_map(|t| ranking(t["teamNumber"].as_u64()? as u32, event_key, &t["stats"]))
+            .collect();
+        rankings.sort_by_key(|r| r.rank);
+        Ok(rankings)
+    }
+
+    fn matches(&self, client: &Client, event_key: &str) -> Result<Vec<Match>, BackendError> {
+        let fields = format!(
+            "matches {{ matchNum series tournamentLevel description actualStartTime \
+             teams {{ teamNumber alliance }} scores {{ {} }} }}",
+            scores_fields()
+        );
+        let event = self.event_query(client, "Matches", &fields, event_key)?;
+        let list = event["matches"]
+            .as_array()
+            .ok_or_else(|| unexpected("matches"))?;
+
+        let mut matches = Vec::new();
+        for json in list.iter() {
+            let number = json["matchNum"]
+                .as_u64()
+                .ok_or_else(|| unexpected("match"))?;
+            let series = json["series"].as_u64().unwrap_or(0) as u32;
+            let round = match json["tournamentLevel"].as_str() {
+                Some("Quals") => Round::Qualification,
+                // Double-elimination playoff series are numbered like semifinal series.
+                Some("Semis") | Some("DoubleElim") => Round::Semifinal(series),
+                Some("Finals") => Round::Final,
+                _ => return Err(unexpected("match")),
+            };
+            let (match_key, tournament_level) = round.match_key(event_key, number as u32);
+
+            let mut red_teams = Vec::new();
+            let mut blue_teams = Vec::new();
+            for team in json["teams"].as_array().into_iter().flatten() {
+                let number = match team["teamNumber"].as_u64() {
+                    Some(n) => n as u32,
+                    None => continue,
+                };
+                match team["alliance"].as_str() {
+                    Some("Red") => red_teams.push(number),
+                    Some("Blue") => blue_teams.push(number),
+                    _ => continue,
+                }
+            }
+
+            let scores = &json["scores"];
+            let score =
+                |alliance: &str, key: &str| scores[alliance][key].as_i64().unwrap_or(0) as i32;
+            // Remote matches are scored for a single team rather than by alliance, and those
+            // scores are not selected, so such matches are left unplayed rather than 0-0.
+            let played =
+                scores["red"]["totalPoints"].is_i64() && scores["blue"]["totalPoints"].is_i64();
+            matches.push(Match {
+                match_key,
+                event_key: event_key.to_string(),
+                scheduled_time: None,
+                match_start_time: json["actualStartTime"]
+                    .as_str()
+                    .filter(|_| played)
+                    .map(|t| t.to_string()),
+                match_name: json["description"].as_str().unwrap_or("").to_string(),
+                tournament_level,
+                red_teams,
+                blue_teams,
+                red_score: score("red", "totalPoints"),
+                blue_score: score("blue", "totalPoints"),
+                // Each alliance is awarded the penalty points the other one committed.
+                red_penalty: score("blue", "penaltyPointsCommitted"),
+                blue_penalty: score("red", "penaltyPointsCommitted"),
+                red_auto_score: score("red", "autoPoints"),
+                blue_auto_score: score("blue", "autoPoints"),
+                red_tele_score: score("red", "dcPoints"),
+                blue_tele_score: score("blue", "dcPoints"),
+                red_end_score: score("red", "egPoints"),
+                blue_end_score: score("blue", "egPoints"),
+            });
+        }
+        Ok(matches)
+    }
+
+    fn team_results(
+        &self,
+        client: &Client,
+        team_number: u32,
+        season: i32,
+    ) -> Result<Vec<Ranking>, BackendError> {
+        let fields = format!(
+            "events(season: $season) {{ eventCode stats {{ {} }} }}",
+            stats_fields()
+        );
+        let team = self.team_query(client, "TeamResults", &fields, team_number, Some(season))?;
+        let events = team["events"]
+            .as_array()
+            .ok_or_else(|| unexpected("team events"))?;
+        Ok(events
+            .iter()
+            .filter_map(|e| {
+                let event_key = format!("{}-{}", season, e["eventCode"].as_str()?);
+                ranking(team_number, &event_key, &e["stats"])
+            })
+            .collect())
+    }
+
+    fn quick_stats(
+        &self,
+        client: &Client,
+        team_number: u32,
+        season: i32,
+    ) -> Result<QuickStats, BackendError> {
+        let team = self.team_query(
+            client,
+            "QuickStats",
+            "quickStats(season: $season) { tot { value rank } auto { value rank } \
+             dc { value rank } eg { value rank } count }",
+            team_number,
+            Some(season),
+        )?;
+        let stats = match &team["quickStats"] {
+            Value::Null => {
+                return Err(BackendError::NotFound(format!(
+                    "Stats of team {} in {}",
+                    team_number, season
+                )))
+            }
+            stats => stats,
+        };
+        Ok(QuickStats {
+            team_number,
+            season,
+            total: ranked_stat(&stats["tot"]),
+            auto: ranked_stat(&stats["auto"]),
+            teleop: ranked_stat(&stats["dc"]),
+            endgame: ranked_stat(&stats["eg"]),
+            events: stats["count"].as_u64().unwrap_or(0) as u32,
+        })
+    }
+
+    fn missing_fields(&self) -> &'static [&'static str] {
+        &[
+            "rank_change",
+            "highest_qual_score",
+            "qualifying_points",
+            "scheduled_time",
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FtcScout;
+    use crate::test_server::{write_query_snapshot, TestServer};
+    use crate::{BackendError, Client, ReqwestTransport, Season};
+    use std::path::{Path, PathBuf};
+
+    fn client(server: &TestServer) -> Client {
+        let http = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        Client::new("")
+            .with_transport(ReqwestTransport::with_client(http))
+            .with_backend(FtcScout::new().with_base_url(server.url()))
+    }
+
+    #[test]
+    fn existing_methods_work_with_ftcscout() {
+        let server = TestServer::ftcscout();
+        let client = client(&server);
+
+        assert_eq!(client.team(16405).opr(Season::PowerPlay), 122.53);
+        assert_eq!(client.team(16405).season_wins(Season::PowerPlay), 10.0);
+        let event = client.event("2223-USTXDAQ");
+        assert_eq!(event.rank(16405), 3.0);
+        assert_eq!(event.opr(8565), 71.9);
+        let rankings = event.rankings();
+        let order: Vec<u32> = rankings.iter().map(|r| r.team_number).collect();
+        assert_eq!(order, vec![8565, 11260, 16405]);
+    }
+
+    #[test]
+    fn maps_teams_events_matches_and_quick_stats() {
+        let server = TestServer::ftcscout();
+        let client = client(&server);
+
+        let team = client.team_info(16405).unwrap();
+        assert_eq!(
+            (&team.name_short[..], team.website),
+            ("Iron Panthers", None)
+        );
+        assert_eq!(
+            client.team_info(99999),
+            Err(BackendError::NotFound("Team 99999".to_string()))
+        );
+        let event = client.event_info("2223-USTXDAQ").unwrap();
+        assert_eq!(event.event_name, "Dallas Qualifier");
+        assert_eq!(event.city.as_deref(), Some("Dallas"));
+
+        let matches = client.event("2223-USTXDAQ").matches();
+        assert_eq!(matches[0].match_key, "2223-USTXDAQ-Q001-1");
+        assert_eq!(matches[0].red_teams, vec![16405, 11260]);
+        assert_eq!((matches[0].red_score, matches[0].blue_score), (145, 98));
+        assert_eq!((matches[0].red_penalty, matches[0].blue_penalty), (10, 0));
+        assert_eq!(matches[0].red_end_score, 25);
+        let keys: Vec<(&str, u32)> = matches
+            .iter()
+            .map(|m| (&m.match_key[..], m.tournament_level))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("2223-USTXDAQ-Q001-1", 1),
+                ("2223-USTXDAQ-E101-1", 21),
+                ("2223-USTXDAQ-E201-1", 22),
+                ("2223-USTXDAQ-F001-1", 4)
+            ]
+        );
+        // The finals have started, but have no alliance scores.
+        assert!(!matches[3].is_qualification() && !matches[3].is_played());
+
+        let stats = client.quick_stats(16405, Season::PowerPlay).unwrap();
+        assert_eq!((stats.total.value, stats.total.rank), (64.12, Some(212)));
+        assert_eq!((stats.teleop.value, stats.events), (30.02, 2));
+        assert_eq!(
+            Client::new("").quick_stats(16405, Season::PowerPlay),
+            Err(BackendError::Unsupported("quick stats"))
+        );
+    }
+
+    /// The operation of every query the backend sends.
+    const OPERATIONS: [&str; 6] = [
+        "Team",
+        "Event",
+        "Rankings",
+        "Matches",
+        "TeamResults",
+        "QuickStats",
+    ];
+
+    fn send_every_query(client: &Client) {
+        client.team_info(16405).unwrap();
+        client.event_info("2223-USTXDAQ").unwrap();
+        client.event("2223-USTXDAQ").rankings();
+        client.event("2223-USTXDAQ").matches();
+        client.team(16405).opr(Season::PowerPlay);
+        client.quick_stats(16405, Season::PowerPlay).unwrap();
+    }
+
+    fn snapshots_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ftcscout/queries")
+    }
+
+    #[test]
+    fn sends_the_snapshotted_queries() {
+        let server = TestServer::ftcscout();
+        send_every_query(&client(&server));
+
+        for operation in OPERATIONS.iter() {
+            let file = snapshots_dir().join(format!("{}.graphql", operation));
+            let snapshot = std::fs::read_to_string(&file).unwrap_or_default();
+            assert_eq!(
+                server.query(operation).as_deref(),
+                Some(snapshot.trim_end()),
+                "{} does not match {}",
+                operation,
+                file.display()
+            );
+        }
+    }
+
+    /// Run with `cargo test update_query_snapshots -- --ignored` after changing a query.
+    #[test]
+    #[ignore]
+    fn update_query_snapshots() {
+        let server = TestServer::ftcscout();
+        send_every_query(&client(&server));
+        for operation in OPERATIONS.iter() {
+            let query = match server.query(operation) {
+                Some(q) => q,
+                None => panic!("Something went wrong: no {} query was sent", operation),
+            };
+            write_query_snapshot(&snapshots_dir(), operation, &query);
+        }
+    }
+}
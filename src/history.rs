@@ -0,0 +1,147 @@
+//! Head-to-head history between two teams.
+
+use crate::Match;
+
+/// The matches two teams played in the same role, either as partners or as opponents.
+///
+/// Wins, losses and ties are counted from the point of view of the first team.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Meetings {
+    /// The keys of the matches, such as `1920-TX-TRQ-Q001-1`.
+    pub match_keys: Vec<String>,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    /// The average score of the two teams combined. As partners this is the score of their
+    /// alliance, and as opponents it is the total score of both alliances.
+    pub average_combined_score: f64,
+}
+
+impl Meetings {
+    /// The number of matches played.
+    pub fn matches(&self) -> u32 {
+        self.match_keys.len() as u32
+    }
+
+    /// The fraction of the matches the first team won, or `0` if there were none.
+    pub fn win_rate(&self) -> f64 {
+        match self.matches() {
+            0 => 0.0,
+            n => self.wins as f64 / n as f64,
+        }
+    }
+}
+
+/// How a team has done with and against another team.
+///
+/// You can get the history of two teams with
+/// [`Team::head_to_head`](struct.Team.html#method.head_to_head).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeadToHead {
+    pub team_number: u32,
+    pub other_team: u32,
+    /// The matches the teams played on the same alliance.
+    pub as_partners: Meetings,
+    /// The matches the teams played on opposite alliances.
+    pub as_opponents: Meetings,
+}
+
+/// Find the matches two teams played together, as partners or as opponents.
+///
+/// Matches that have not been played yet are ignored.
+///
+/// # Arguments
+///
+/// * `team_number` - The team whose wins and losses are counted.
+/// * `other_team` - The other team.
+/// * `matches` - The matches to look through, such as every match of both teams' events.
+pub fn head_to_head(team_number: u32, other_team: u32, matches: &[Match]) -> HeadToHead {
+    let mut history = HeadToHead {
+        team_number,
+        other_team,
+        ..Default::default()
+    };
+    let mut partner_total = 0.0;
+    let mut opponent_total = 0.0;
+
+    for m in matches.iter() {
+        if !m.is_played() || !m.has_team(team_number) || !m.has_team(other_team) {
+            continue;
+        }
+        let red = m.red_teams.contains(&team_number);
+        let (own, other) = if red {
+            (m.red_score, m.blue_score)
+        } else {
+            (m.blue_score, m.red_score)
+        };
+        let meetings = if red == m.red_teams.contains(&other_team) {
+            partner_total += own as f64;
+            &mut history.as_partners
+        } else {
+            opponent_total += (own + other) as f64;
+            &mut history.as_opponents
+        };
+        if own > other {
+            meetings.wins += 1;
+        } else if own < other {
+            meetings.losses += 1;
+        } else {
+            meetings.ties += 1;
+        }
+        meetings.match_keys.push(m.match_key.clone());
+    }
+
+    for (meetings, total) in [
+        (&mut history.as_partners, partner_total),
+        (&mut history.as_opponents, opponent_total),
+    ]
+    .iter_mut()
+    {
+        if meetings.matches() > 0 {
+            meetings.average_combined_score = *total / meetings.matches() as f64;
+        }
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::head_to_head;
+    use crate::Match;
+
+    fn game(key: &str, red: [u32; 2], blue: [u32; 2], red_score: i32, blue_score: i32) -> Match {
+        Match {
+            match_key: key.to_string(),
+            match_start_time: Some("2019-12-07T15:00:00.000Z".to_string()),
+            red_teams: red.to_vec(),
+            blue_teams: blue.to_vec(),
+            red_score,
+            blue_score,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn splits_partners_and_opponents() {
+        let mut unplayed = game("Q4", [1, 2], [3, 4], 0, 0);
+        unplayed.match_start_time = None;
+        let matches = vec![
+            game("Q1", [1, 2], [3, 4], 100, 80),
+            game("Q2", [3, 1], [2, 4], 60, 90),
+            game("Q3", [3, 4], [2, 1], 70, 70),
+            game("Q5", [3, 4], [5, 6], 70, 50),
+            unplayed,
+        ];
+        let history = head_to_head(1, 2, &matches);
+        assert_eq!(history.as_partners.match_keys, vec!["Q1", "Q3"]);
+        assert_eq!(history.as_partners.wins, 1);
+        assert_eq!(history.as_partners.ties, 1);
+        assert_eq!(history.as_partners.average_combined_score, 85.0);
+        assert_eq!(history.as_opponents.match_keys, vec!["Q2"]);
+        assert_eq!(history.as_opponents.losses, 1);
+        assert_eq!(history.as_opponents.win_rate(), 0.0);
+        assert_eq!(history.as_opponents.average_combined_score, 150.0);
+    }
+}
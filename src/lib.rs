@@ -4,42 +4,278 @@
 //! This crate makes it easy to access the official First Tech Challenge API
 //! and use it in your Rust projects.
 
-use reqwest::blocking::Response;
-use reqwest::header::CONTENT_TYPE;
+pub mod backend;
+mod cache;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+mod disk_cache;
+mod event_list;
+#[cfg(feature = "csv")]
+pub mod export;
+mod ftcscout;
+pub mod history;
+mod limiter;
+mod lookup;
+mod matches;
+pub mod merge;
+mod metrics;
+pub mod notify;
+pub mod picklist;
+mod ranking;
+mod ratings;
+mod report;
+#[cfg(feature = "serde")]
+mod serde_support;
+pub mod simulation;
+pub mod stats;
+#[cfg(feature = "sqlite")]
+pub mod sync;
+#[cfg(test)]
+mod test_server;
+mod transport;
+pub mod watch;
+
+pub use backend::{Backend, BackendError, QuickStats, RankedStat, TeamInfo};
+pub use cache::CachePolicy;
+pub use disk_cache::{DiskCache, StoredResponse};
+pub use event_list::{EventInfo, EventList, EventSummary};
+pub use history::{HeadToHead, Meetings};
+pub use lookup::TeamLookupError;
+pub use matches::Match;
+pub use metrics::Metrics;
+pub use ranking::Ranking;
+pub use ratings::{Elo, RatingPoint};
+pub use report::{EventResult, SeasonReport};
+pub use simulation::RankDistribution;
+pub use stats::{ScheduleStrength, TeamStats};
+pub use transport::{
+    FixtureTransport, ReqwestTransport, Transport, TransportRequest, TransportResponse,
+};
+
+use backend::TheOrangeAlliance;
+use cache::ResponseCache;
+use limiter::RateLimiter;
+use metrics::Counters;
 use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 /// The main RusTOA client.
 ///
 /// You can use the [Client](struct.Client.html) to get the API version
 /// and create a [Team](struct.Team.html) object.
+///
+/// A client is a cheap handle that can be cloned and shared between threads. Every clone,
+/// including the ones held by [`Team`](struct.Team.html) and [`Event`](struct.Event.html)
+/// objects, shares one connection pool, cache, rate limit and set of
+/// [`Metrics`](struct.Metrics.html).
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let client = rustoa::Client::new("api_key")
+///     .with_cache(rustoa::CachePolicy::default())
+///     .with_rate_limit(30, Duration::from_secs(60));
+/// let handles: Vec<_> = [16405, 8565]
+///     .iter()
+///     .map(|&n| {
+///         let client = client.clone();
+///         std::thread::spawn(move || client.team(n).wins())
+///     })
+///     .collect();
+/// for handle in handles {
+///     println!("{}", handle.join().unwrap());
+/// }
+/// println!("{} requests", client.metrics().requests);
+/// ```
 #[derive(Clone, Debug)]
 pub struct Client {
+    inner: Arc<ClientInner>,
+}
+
+#[derive(Clone, Debug)]
+struct ClientInner {
     api_key: String,
     application_name: String,
+    base_url: String,
+    transport: Arc<dyn Transport>,
+    backend: Arc<dyn Backend>,
+    cache: Option<Arc<Mutex<ResponseCache>>>,
+    disk_cache: Option<DiskCache>,
+    limiter: Option<Arc<RateLimiter>>,
+    retry_pause: std::time::Duration,
+    stale: Arc<Mutex<HashMap<String, SystemTime>>>,
+    metrics: Arc<Counters>,
+}
+
+/// The raw body of an API response, along with where it came from.
+///
+/// This is returned by [`Client::raw`](struct.Client.html#method.raw).
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    /// The raw response body.
+    pub body: String,
+    /// When the response was fetched from the API.
+    pub fetched_at: SystemTime,
+    /// Whether the API could not be reached and this response was served from the
+    /// [`DiskCache`](struct.DiskCache.html) instead.
+    pub stale: bool,
 }
 
 impl Client {
     #[doc(hidden)]
-    pub fn request(&self, target: &str) -> Result<Response, Box<dyn std::error::Error>> {
-        let url = format!("https://theorangealliance.org/api{}", target);
-        let client = reqwest::blocking::Client::new();
-        let resp = client
-            .get(&url[..])
-            .header("X-TOA-Key", &self.api_key)
-            .header("X-Application-Origin", &self.application_name)
-            .header(CONTENT_TYPE, "application/json")
-            .send()?;
-
-        Ok(resp)
+    pub fn request(&self, target: &str) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        let request = TransportRequest {
+            url: format!("{}{}", self.inner.base_url, target),
+            path: target.to_string(),
+            headers: vec![
+                ("X-TOA-Key".to_string(), self.inner.api_key.clone()),
+                (
+                    "X-Application-Origin".to_string(),
+                    self.inner.application_name.clone(),
+                ),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ],
+        };
+        self.send(&request)
+    }
+    /// Send a request through this client's transport, rate limit and metrics.
+    #[doc(hidden)]
+    pub fn send(
+        &self,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        self.counted(|| self.inner.transport.get(request))
+    }
+    /// Send a POST request through this client's transport, rate limit and metrics.
+    #[doc(hidden)]
+    pub fn send_post(
+        &self,
+        request: &TransportRequest,
+        body: &str,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        self.counted(|| self.inner.transport.post(request, body))
+    }
+    fn counted<F>(&self, send: F) -> Result<TransportResponse, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Result<TransportResponse, Box<dyn std::error::Error>>,
+    {
+        if let Some(limiter) = &self.inner.limiter {
+            if limiter.acquire() {
+                self.inner.metrics.throttled();
+            }
+        }
+        self.inner.metrics.request();
+        let resp = send();
+        match &resp {
+            Ok(r) if r.status == 429 => {
+                self.inner.metrics.rate_limited();
+                self.inner.metrics.error();
+            }
+            Ok(r) if r.status >= 400 => self.inner.metrics.error(),
+            Ok(_) => {}
+            Err(_) => self.inner.metrics.error(),
+        }
+        resp
+    }
+    fn fetch(&self, target: &str) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(self.raw(target)?.body)
+    }
+    fn fetch_live(&self, target: &str) -> Result<(bool, String), Box<dyn std::error::Error>> {
+        let resp = self.request(target)?;
+        if resp.is_server_error() || resp.status == 429 {
+            return Err(format!("The API returned {}", resp.status).into());
+        }
+        Ok((resp.is_success(), resp.body))
+    }
+    fn stored(&self, stored: StoredResponse, stale: bool) -> RawResponse {
+        let mut stale_paths = self.inner.stale.lock().unwrap();
+        if stale {
+            stale_paths.insert(stored.path, stored.fetched_at);
+        } else {
+            stale_paths.remove(&stored.path);
+        }
+        RawResponse {
+            body: stored.body,
+            fetched_at: stored.fetched_at,
+            stale,
+        }
+    }
+
+    /// Make a request to the API and return the raw response body.
+    ///
+    /// The in-memory cache and the disk cache are used if they are enabled.
+    /// When the API cannot be reached, fails or is rate limiting the client and a stored
+    /// response exists on disk, that response is returned with `stale` set to `true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The request path, such as `/event/1920-TX-TRQ/rankings`.
+    pub fn raw(&self, target: &str) -> Result<RawResponse, Box<dyn std::error::Error>> {
+        if let Some(cached) = self.cached(target) {
+            return Ok(cached);
+        }
+
+        let (success, body) = match self.fetch_live(target) {
+            Ok(r) => r,
+            Err(e) => match self.inner.disk_cache.as_ref().and_then(|d| d.get(target)) {
+                Some(stored) => return Ok(self.stored(stored, true)),
+                None => return Err(e),
+            },
+        };
+        let fetched_at = SystemTime::now();
+        if success {
+            self.remember(target, &body, fetched_at);
+        }
+
+        Ok(RawResponse {
+            body,
+            fetched_at,
+            stale: false,
+        })
+    }
+    /// The response for a request path from the in-memory cache or a fresh disk cache entry.
+    pub(crate) fn cached(&self, target: &str) -> Option<RawResponse> {
+        if let Some(cache) = &self.inner.cache {
+            if let Some((body, fetched_at)) = cache.lock().unwrap().get(target) {
+                self.inner.metrics.cache_hit();
+                return Some(RawResponse {
+                    body,
+                    fetched_at,
+                    stale: false,
+                });
+            }
+        }
+        if let Some(disk) = &self.inner.disk_cache {
+            if let Some(stored) = disk.get_fresh(target) {
+                self.inner.metrics.cache_hit();
+                return Some(self.stored(stored, false));
+            }
+        }
+        None
+    }
+    /// Store a successful response in the caches that are enabled.
+    pub(crate) fn remember(&self, target: &str, body: &str, fetched_at: SystemTime) {
+        if let Some(cache) = &self.inner.cache {
+            cache
+                .lock()
+                .unwrap()
+                .insert(target, body.to_string(), fetched_at);
+        }
+        if let Some(disk) = &self.inner.disk_cache {
+            // The response is still good if it could not be stored.
+            let _ = disk.put(target, body);
+        }
+        self.inner.stale.lock().unwrap().remove(target);
     }
     #[doc(hidden)]
     pub fn api_key(&self) -> &str {
-        &self.api_key[..]
+        &self.inner.api_key[..]
     }
     #[doc(hidden)]
     pub fn application_name(&self) -> &str {
-        &self.application_name[..]
+        &self.inner.application_name[..]
     }
 
     /// Create a new Client object.
@@ -50,9 +286,213 @@ impl Client {
     /// It returns a [Client](struct.Client.html) object.
     pub fn new(api_key: &str) -> Client {
         Client {
-            api_key: api_key.to_string(),
-            application_name: "rustoa".to_string(),
+            inner: Arc::new(ClientInner {
+                api_key: api_key.to_string(),
+                application_name: "rustoa".to_string(),
+                base_url: "https://theorangealliance.org/api".to_string(),
+                transport: Arc::new(ReqwestTransport::new()),
+                backend: Arc::new(TheOrangeAlliance),
+                cache: None,
+                disk_cache: None,
+                limiter: None,
+                retry_pause: std::time::Duration::from_secs(1),
+                stale: Arc::new(Mutex::new(HashMap::new())),
+                metrics: Arc::new(Counters::default()),
+            }),
+        }
+    }
+
+    /// The settings of this client, copied first if other clones exist so that the
+    /// `with_*` methods never change a client that is already in use.
+    fn config(&mut self) -> &mut ClientInner {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// Limit how many requests this client and all of its clones send.
+    ///
+    /// Requests over the limit wait until they can be sent, rather than being rejected
+    /// by the API.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests` - The most requests to send in any window of length `per`.
+    /// * `per` - The length of the window.
+    pub fn with_rate_limit(mut self, requests: u32, per: std::time::Duration) -> Client {
+        self.config().limiter = Some(Arc::new(RateLimiter::new(requests, per)));
+        self
+    }
+
+    /// How long team lookups wait before trying again when the API rate limits them without
+    /// saying how long to wait. The default is one second.
+    ///
+    /// See [`team_checked`](#method.team_checked) and [`teams_bulk`](#method.teams_bulk).
+    pub fn with_retry_pause(mut self, pause: std::time::Duration) -> Client {
+        self.config().retry_pause = pause;
+        self
+    }
+
+    pub(crate) fn retry_pause(&self) -> std::time::Duration {
+        self.inner.retry_pause
+    }
+
+    /// What this client and all of its clones have done so far.
+    pub fn metrics(&self) -> Metrics {
+        self.inner.metrics.snapshot()
+    }
+
+    /// Send requests to a different server instead of The Orange Alliance.
+    ///
+    /// This is useful for testing against a local server that serves canned responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The URL that request paths are appended to, such as
+    ///   `http://127.0.0.1:8080`. It should not end with a slash.
+    pub fn with_base_url(mut self, base_url: &str) -> Client {
+        self.config().base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send requests through a custom [`Transport`](trait.Transport.html) instead of the
+    /// default `reqwest` client.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - The transport to use, such as a
+    ///   [`FixtureTransport`](struct.FixtureTransport.html) in tests.
+    pub fn with_transport<T: Transport + 'static>(mut self, transport: T) -> Client {
+        self.config().transport = Arc::new(transport);
+        self
+    }
+
+    /// Get team, event, ranking and match data from another source than The Orange Alliance.
+    ///
+    /// See the [`backend`](backend/index.html) module for which methods use the backend. The
+    /// others still ask The Orange Alliance, but a backend may have its own event keys, such as
+    /// `1920-USTXTRQ` rather than `1920-TX-TRQ`. When it does, the methods of an
+    /// [`Event`](struct.Event.html) that ask The Orange Alliance, such as
+    /// [`properties`](struct.Event.html#method.properties),
+    /// [`match_details`](struct.Event.html#method.match_details) and
+    /// [`watch`](struct.Event.html#method.watch), panic rather than send it a key it does not
+    /// know.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The backend to use, such as
+    ///   [`FtcEvents`](backend/struct.FtcEvents.html).
+    pub fn with_backend<B: Backend + 'static>(mut self, backend: B) -> Client {
+        self.config().backend = Arc::new(backend);
+        self
+    }
+
+    /// The backend this client gets its data from.
+    pub fn backend(&self) -> &dyn Backend {
+        &*self.inner.backend
+    }
+
+    /// Enable the in-memory response cache.
+    ///
+    /// Responses are cached by request path and kept for as long as the
+    /// [`CachePolicy`](struct.CachePolicy.html) allows. The cache is shared by every clone of
+    /// this client, including the ones held by [`Team`](struct.Team.html) and
+    /// [`Event`](struct.Event.html) objects created from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The TTLs to use for each endpoint.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let client = rustoa::Client::new("api_key").with_cache(rustoa::CachePolicy::default());
+    /// let team = client.team(16405);
+    /// // Only the first of these makes a request.
+    /// let record = (team.wins(), team.losses(), team.ties());
+    /// ```
+    pub fn with_cache(mut self, policy: CachePolicy) -> Client {
+        self.config().cache = Some(Arc::new(Mutex::new(ResponseCache::new(policy))));
+        self
+    }
+
+    /// Remove the cached response for a single request path, such as `/team/16405/wlt`.
+    ///
+    /// This does nothing if the cache is not enabled.
+    pub fn invalidate(&self, path: &str) {
+        if let Some(cache) = &self.inner.cache {
+            cache.lock().unwrap().invalidate(path);
+        }
+    }
+
+    /// Remove every cached response whose request path matches `pattern`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `pattern` is not a valid regular expression.
+    pub fn invalidate_matching(&self, pattern: &str) {
+        let re = match regex::Regex::new(pattern) {
+            Ok(r) => r,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+        if let Some(cache) = &self.inner.cache {
+            cache.lock().unwrap().invalidate_matching(&re);
+        }
+    }
+
+    /// Remove every cached response.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.inner.cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /// Store every response on disk, and fall back to the stored responses when the API
+    /// cannot be reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `disk_cache` - The [`DiskCache`](struct.DiskCache.html) to use.
+    pub fn with_disk_cache(mut self, disk_cache: DiskCache) -> Client {
+        self.config().disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Whether the last response for a request path was served from the disk cache
+    /// because the API could not be reached.
+    ///
+    /// ```no_run
+    /// # let client = rustoa::Client::new("api_key");
+    /// let event = client.event("1920-TX-TRQ");
+    /// let rank = event.rank(16405);
+    /// if client.is_stale("/event/1920-TX-TRQ/rankings") {
+    ///     println!("Offline, showing the last known rank: {}", rank);
+    /// }
+    /// ```
+    pub fn is_stale(&self, path: &str) -> bool {
+        self.inner.stale.lock().unwrap().contains_key(path)
+    }
+
+    /// Every request path that was last served from the disk cache, with the time its
+    /// stored response was originally fetched.
+    pub fn stale_responses(&self) -> HashMap<String, SystemTime> {
+        self.inner.stale.lock().unwrap().clone()
+    }
+
+    /// Fetch and store the data of the given events, so that it is available offline.
+    ///
+    /// This requests the event information, rankings, matches and teams of every event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event_keys` - The keys of the events, such as `1920-TX-TRQ`.
+    pub fn prewarm(&self, event_keys: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        for key in event_keys.iter() {
+            for suffix in ["", "/rankings", "/matches", "/teams"].iter() {
+                let resp = self.raw(&format!("/event/{}{}", key, suffix))?;
+                if resp.stale {
+                    return Err(format!("Could not reach the API to fetch {}", key).into());
+                }
+            }
         }
+        Ok(())
     }
 
     /// Get the version of The Orange Alliance API that this crate is using.
@@ -61,20 +501,20 @@ impl Client {
     /// # Panics
     /// This method can panic in three ways:
     /// - The HTTP request to the API fails. This can be because the API is either down or you are
-    /// being ratelimited.
+    ///   being ratelimited.
     /// - Serde cannot properly deserialize the JSON data in the response. This happens because the
-    /// API has sent invalid JSON.
+    ///   API has sent invalid JSON.
     /// - The HashMap does not have the needed keys to process the data. This happens because
-    /// the request was made to the wrong target or the API has sent back an error in JSON form.
+    ///   the request was made to the wrong target or the API has sent back an error in JSON form.
     pub fn api_version(&self) -> String {
-        let resp = match self.request("/") {
+        let resp = match self.fetch("/") {
             Ok(resp) => resp,
             Err(e) => {
                 panic!("Something went wrong: {}", e);
             }
         };
 
-        let map = match resp.json::<HashMap<String, String>>() {
+        let map = match serde_json::from_str::<HashMap<String, String>>(&resp) {
             Ok(m) => m,
             Err(e) => panic!("Something went wrong: {}", e),
         };
@@ -93,6 +533,94 @@ impl Client {
     pub fn team(&self, team_number: u32) -> Team {
         Team::new(team_number, self.clone())
     }
+    /// Get an instance of [`Team`](struct.Team.html), after checking that the team exists.
+    /// # Arguments
+    ///
+    /// * `team_number` - The FTC team number as a `u32` integer.
+    ///
+    /// It returns a [`TeamLookupError`](enum.TeamLookupError.html) if the API has no team
+    /// with this number, or if the API could not be asked.
+    pub fn team_checked(&self, team_number: u32) -> Result<Team, TeamLookupError> {
+        lookup::team_checked(self, team_number)
+    }
+    /// Check that many teams exist, such as the team numbers of a registration spreadsheet.
+    /// # Arguments
+    ///
+    /// * `team_numbers` - The FTC team numbers to look up.
+    ///
+    /// The teams are looked up a few at a time. When the API rate limits a lookup, every
+    /// lookup pauses before trying again.
+    ///
+    /// It returns one result per team number, in the same order.
+    pub fn teams_bulk(&self, team_numbers: &[u32]) -> Vec<Result<Team, TeamLookupError>> {
+        lookup::teams_bulk(self, team_numbers, lookup::BULK_WORKERS)
+    }
+    /// The details of a team, from the client's [`Backend`](trait.Backend.html).
+    /// # Arguments
+    ///
+    /// * `team_number` - The FTC team number as a `u32` integer.
+    pub fn team_info(&self, team_number: u32) -> Result<TeamInfo, BackendError> {
+        self.inner.backend.team(self, team_number)
+    }
+    /// The details of an event, from the client's [`Backend`](trait.Backend.html).
+    /// # Arguments
+    ///
+    /// * `event_key` - The key of the event, such as `1920-TX-TRQ`.
+    pub fn event_info(&self, event_key: &str) -> Result<EventInfo, BackendError> {
+        self.inner.backend.event(self, event_key)
+    }
+    /// A summary of a team's season, from the client's [`Backend`](trait.Backend.html).
+    ///
+    /// Only the [`FtcScout`](backend/struct.FtcScout.html) backend provides these.
+    /// # Arguments
+    ///
+    /// * `team_number` - The FTC team number as a `u32` integer.
+    /// * [`season: Season`](enum.Season.html) - A rustoa `Season` object.
+    pub fn quick_stats(
+        &self,
+        team_number: u32,
+        season: Season,
+    ) -> Result<QuickStats, BackendError> {
+        self.inner
+            .backend
+            .quick_stats(self, team_number, season.value())
+    }
+    /// This method is used to get an instance of [`Event`](struct.Event.html).
+    /// # Arguments
+    ///
+    /// * `event_key` - The key of the event, such as `1920-TX-TRQ`.
+    ///
+    /// It returns an [Event](struct.Event.html) object.
+    pub fn event(&self, event_key: &str) -> Event {
+        Event::new(event_key, self)
+    }
+    /// Get a single match by its key.
+    /// # Arguments
+    ///
+    /// * `match_key` - The key of the match, such as `1920-TX-TRQ-Q001-1`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic in the following ways:
+    /// - The HTTP request was not successful
+    /// - The data received from the API was invalid JSON
+    /// - The match does not exist, or the data received was in the wrong format
+    pub fn match_result(&self, match_key: &str) -> Match {
+        let resp = match self.fetch(&format!("/match/{}", match_key)) {
+            Ok(r) => r,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+
+        let json: serde_json::Value = match serde_json::from_str(&resp[..]) {
+            Ok(v) => v,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+
+        match Match::from_json(&json[0]) {
+            Some(m) => m,
+            None => panic!("Something went wrong"),
+        }
+    }
 }
 
 /// A struct used to access an FTC team.
@@ -117,18 +645,16 @@ impl Team {
     fn get_wlt(&self) -> HashMap<String, u32, RandomState> {
         let resp = match self
             .client
-            .request(&format!("/team/{}/wlt", self.team_number)[..])
+            .fetch(&format!("/team/{}/wlt", self.team_number)[..])
         {
             Ok(r) => r,
             Err(e) => panic!("Something went wrong: {}", e),
         };
 
-        let map = match resp.json::<Vec<HashMap<String, u32>>>() {
+        match serde_json::from_str::<Vec<HashMap<String, u32>>>(&resp) {
             Ok(m) => m[0].clone(),
             Err(e) => panic!("Something went wrong: {}", e),
-        };
-
-        map
+        }
     }
     /// The total amount of times the team has won a match.
     ///
@@ -139,7 +665,7 @@ impl Team {
         let map = self.get_wlt();
 
         match map.get("wins") {
-            Some(w) => w.clone(),
+            Some(w) => *w,
             None => panic!("Something went wrong with the API."),
         }
     }
@@ -152,7 +678,7 @@ impl Team {
         let map = self.get_wlt();
 
         match map.get("losses") {
-            Some(l) => l.clone(),
+            Some(l) => *l,
             None => panic!("Something went wrong with the API."),
         }
     }
@@ -165,7 +691,7 @@ impl Team {
         let map = self.get_wlt();
 
         match map.get("ties") {
-            Some(t) => t.clone(),
+            Some(t) => *t,
             None => panic!("Something went wrong with the API."),
         }
     }
@@ -185,16 +711,13 @@ impl Team {
     pub fn properties(&self) -> HashMap<String, String, RandomState> {
         let resp = match self
             .client
-            .request(&format!("/team/{}/", self.team_number)[..])
+            .fetch(&format!("/team/{}/", self.team_number)[..])
         {
             Ok(resp) => resp,
             Err(e) => panic!("Something went wrong: {}", e),
         };
 
-        let map: serde_json::Value = match serde_json::from_str(&*match resp.text() {
-            Ok(text) => text,
-            Err(e) => panic!("Something went wrong: {}", e),
-        }) {
+        let map: serde_json::Value = match serde_json::from_str(&resp) {
             Ok(m) => m,
             Err(e) => panic!("Something went wrong: {}", e),
         };
@@ -225,7 +748,7 @@ impl Team {
                 _ => panic!("Something went wrong"),
             };
             let key_orig = key.clone();
-            if key == "last_active".to_string() {
+            if key == "last_active" {
                 let season = Season::value_of(value.clone());
                 let season = format!("{}", season);
                 new_map.insert(key_orig, season);
@@ -239,24 +762,18 @@ impl Team {
     fn get_season_data(
         &self,
         season: Season,
-        query: &str,
+        query: &'static str,
     ) -> Result<f64, Box<dyn std::error::Error>> {
-        let season = season.value();
-        let resp = self
-            .client
-            .request(&format!("/team/{}/results/{}", self.team_number, season)[..])?;
-        let map: serde_json::Value = serde_json::from_str(&*resp.text()?)?;
-
-        let arr = match map.as_array() {
-            Some(a) => a,
-            None => panic!("Something went wrong"),
-        };
-        let query = query.to_string();
+        if self.client.backend().missing_fields().contains(&query) {
+            return Err(Box::new(BackendError::Unsupported(query)));
+        }
+        let results =
+            self.client
+                .backend()
+                .team_results(&self.client, self.team_number, season.value())?;
         let mut i = 0 as f64;
-        for val in arr.iter() {
-            let val = val.clone();
-            let val = &val[&query];
-            let num = match val.as_f64() {
+        for result in results.iter() {
+            let num = match result.field(query) {
                 Some(n) => n,
                 None => panic!("Something went wrong"),
             };
@@ -333,7 +850,8 @@ impl Team {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API was in the wrong format.
+    /// This method will panic if the data sent by the API was in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn opr(&self, season: Season) -> f64 {
         let data = match self.get_season_data(season, "opr") {
             Ok(m) => m,
@@ -351,7 +869,8 @@ impl Team {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API was in the wrong format.
+    /// This method will panic if the data sent by the API was in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn np_opr(&self, season: Season) -> f64 {
         let data = match self.get_season_data(season, "np_opr") {
             Ok(m) => m,
@@ -393,7 +912,8 @@ impl Team {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API was in the wrong format.
+    /// This method will panic if the data sent by the API was in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn qualifying_points(&self, season: Season) -> f64 {
         let data = match self.get_season_data(season, "qualifying_points") {
             Ok(m) => m,
@@ -422,18 +942,240 @@ impl Team {
         data
     }
 
+    /// A report of the team's season: its result at every event, along with its overall
+    /// record, best and average OPR, best rank, awards and advancement.
+    ///
+    /// The details of the events are read from [`event_list`](#method.event_list), so no
+    /// request is made per event.
+    ///
+    /// ```no_run
+    /// # let client = rustoa::Client::new("api_key");
+    /// let report = client.team(16405).season_report(rustoa::Season::SkyStone);
+    /// println!("{}", report.to_markdown());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method can panic in the following ways:
+    /// - The HTTP request was not successful
+    /// - The data received from the API was invalid JSON
+    /// - The data received was in the wrong format
+    pub fn season_report(&self, season: Season) -> SeasonReport {
+        let season_key = season.value();
+        let attended = self.event_list(season);
+        let season = season_key;
+        let get = |target: String| -> serde_json::Value {
+            let resp = match self.client.fetch(&target) {
+                Ok(r) => r,
+                Err(e) => panic!("Something went wrong: {}", e),
+            };
+            match serde_json::from_str(&resp) {
+                Ok(v) => v,
+                Err(e) => panic!("Something went wrong: {}", e),
+            }
+        };
+
+        let results = get(format!("/team/{}/results/{}", self.team_number, season));
+        let results = match results.as_array() {
+            Some(a) => a.clone(),
+            None => panic!("Something went wrong"),
+        };
+        // Teams without awards get an error object instead of an empty list.
+        let awards = get(format!("/team/{}/awards/{}", self.team_number, season));
+        let awards = awards.as_array().cloned().unwrap_or_default();
+
+        let mut events = Vec::new();
+        for result in results.iter() {
+            let ranking = match Ranking::from_json(result) {
+                Some(r) => r,
+                None => panic!("Something went wrong"),
+            };
+            let info = match attended.iter().find(|e| e.event_key == ranking.event_key) {
+                Some(e) => e,
+                None => panic!(
+                    "Something went wrong: event {} was not found",
+                    ranking.event_key
+                ),
+            };
+            let advanced_to = match &info.advance_event {
+                Some(next) if attended.iter().any(|e| &e.event_key == next) => Some(next.clone()),
+                _ => None,
+            };
+            events.push((
+                info.start_date.clone(),
+                EventResult {
+                    event_key: ranking.event_key.clone(),
+                    event_name: info.event_name.clone(),
+                    awards: awards
+                        .iter()
+                        .filter(|a| a["event_key"].as_str() == Some(&ranking.event_key[..]))
+                        .filter_map(|a| a["award_name"].as_str())
+                        .map(|a| a.to_string())
+                        .collect(),
+                    ranking,
+                    advanced_to,
+                },
+            ));
+        }
+        events.sort_by(|a, b| a.0.cmp(&b.0));
+
+        SeasonReport {
+            team_number: self.team_number,
+            season,
+            events: events.into_iter().map(|(_, e)| e).collect(),
+        }
+    }
+
+    /// How this team has done with and against another team over the given seasons.
+    ///
+    /// Only the matches of this team's events are fetched, since those are the only ones
+    /// the two teams can have played together.
+    ///
+    /// ```no_run
+    /// # let client = rustoa::Client::new("api_key");
+    /// let history = client
+    ///     .team(16405)
+    ///     .head_to_head(8565, &[rustoa::Season::SkyStone]);
+    /// println!("{:.0}% won as opponents", history.as_opponents.win_rate() * 100.0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the events of this team or their matches cannot be fetched,
+    /// or if the client has a [`Backend`](trait.Backend.html) other than The Orange Alliance,
+    /// which lists the events of the team.
+    pub fn head_to_head(&self, other_team: u32, seasons: &[Season]) -> HeadToHead {
+        if !self.client.backend().uses_toa_keys() {
+            panic!(
+                "Something went wrong: head-to-head records are only available from The Orange \
+                 Alliance"
+            );
+        }
+        let mut matches = Vec::new();
+        for season in seasons.iter() {
+            for key in self.event_keys(season).iter() {
+                matches.extend(Event::new(key, &self.client).matches());
+            }
+        }
+        history::head_to_head(self.team_number, other_team, &matches)
+    }
+
+    /// The events the team attended in a season, keyed by a name-derived slug.
+    ///
+    /// The keys are event names in lowercase with underscores, such as
+    /// `trinity_river_qualifier`. When two events share a name, the second one's key ends with
+    /// part of its event key. Prefer [`event_list`](#method.event_list), which keeps the real
+    /// event keys and the order the events were played in.
+    ///
+    /// # Panics
+    ///
+    /// This method panics in the same ways as [`event_list`](#method.event_list).
     pub fn events(&self, season: Season) -> HashMap<String, Event, RandomState> {
+        let mut emap: HashMap<String, Event> = HashMap::new();
+        let re = regex::Regex::new(r"\d{4}-\w+-").unwrap();
+
+        for summary in self.event_list(season).iter() {
+            let mut key = summary.slug();
+            if emap.contains_key(&key[..]) {
+                let raw_key_right = re.replace_all(&summary.event_key[..], "");
+                key = format!("{}_{}", key, raw_key_right.to_lowercase());
+            }
+            emap.insert(key, Event::new(&summary.event_key, &self.client));
+        }
+
+        emap
+    }
+
+    /// The events the team attended in a season, in the order they were played.
+    ///
+    /// ```no_run
+    /// # let client = rustoa::Client::new("api_key");
+    /// let events = client.team(16405).event_list(rustoa::Season::SkyStone);
+    /// for event in events.iter() {
+    ///     println!("{} {}", event.event_key, event.event_name);
+    /// }
+    /// let trq = events.find_by_slug("trinity_river_qualifier");
+    /// ```
+    ///
+    /// The event details come with the team's events when the API includes them. Otherwise
+    /// they are taken from a single request for the season's events, rather than one request
+    /// per event. Only events missing from that listing are requested one by one.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic in the following ways:
+    /// - The HTTP request was not successful
+    /// - The data received from the API was invalid JSON
+    /// - The data received was in the wrong format
+    pub fn event_list(&self, season: Season) -> EventList {
+        let get = |target: String| -> serde_json::Value {
+            let resp = match self.client.fetch(&target) {
+                Ok(r) => r,
+                Err(e) => panic!("Something went wrong: {}", e),
+            };
+            match serde_json::from_str(&resp) {
+                Ok(v) => v,
+                Err(e) => panic!("Something went wrong: {}", e),
+            }
+        };
+
+        let attended = get(format!(
+            "/team/{}/events/{}",
+            self.team_number,
+            season.value()
+        ));
+        let attended = match attended.as_array() {
+            Some(a) => a.clone(),
+            None => panic!("Something went wrong"),
+        };
+
+        let mut events = Vec::new();
+        let mut missing = Vec::new();
+        for participant in attended.iter() {
+            let key = match participant["event_key"].as_str() {
+                Some(k) => k,
+                None => panic!("Something went wrong"),
+            };
+            let details = EventSummary::from_json(&participant["event"])
+                .or_else(|| EventSummary::from_json(participant));
+            match details {
+                Some(e) => events.push(e),
+                None => missing.push(key.to_string()),
+            }
+        }
+
+        if !missing.is_empty() {
+            let listing = get(format!("/event?season_key={}", season.value()));
+            let listing = match listing.as_array() {
+                Some(a) => a.clone(),
+                None => panic!("Something went wrong"),
+            };
+            for key in missing.iter() {
+                let found = listing
+                    .iter()
+                    .find(|e| e["event_key"].as_str() == Some(&key[..]))
+                    .and_then(EventSummary::from_json)
+                    // Events missing from the listing are asked for one at a time.
+                    .or_else(|| EventSummary::from_json(&get(format!("/event/{}", key))[0]));
+                match found {
+                    Some(e) => events.push(e),
+                    None => panic!("Something went wrong: event {} was not found", key),
+                }
+            }
+        }
+
+        EventList::new(events)
+    }
+
+    fn event_keys(&self, season: &Season) -> Vec<String> {
         let resp = match self
             .client
-            .request(&format!("/team/{}/events/{}", self.team_number, season.value())[..])
+            .fetch(&format!("/team/{}/events/{}", self.team_number, season.value())[..])
         {
-            Ok(r) => match r.text() {
-                Ok(t) => t,
-                Err(e) => panic!("Something went wrong: {}", e),
-            },
+            Ok(r) => r,
             Err(e) => panic!("Something went wrong: {}", e),
         };
-        let json: serde_json::Value = match serde_json::from_str(&*resp) {
+        let json: serde_json::Value = match serde_json::from_str(&resp) {
             Ok(m) => m,
             Err(e) => panic!("Something went wrong: {}", e),
         };
@@ -452,24 +1194,7 @@ impl Team {
             };
             keys.push(key);
         }
-
-        let mut emap: HashMap<String, Event> = HashMap::new();
-
-        for key in keys.iter() {
-            let event_key = key.clone();
-            let event = Event::new(&*key.clone(), &self.client);
-            let raw_key = event.name();
-            let mut key = raw_key.replace(" ", "_");
-            key = key.to_lowercase();
-            if emap.contains_key(&key[..]) {
-                let re = regex::Regex::new(r"\d{4}-\w+-").unwrap();
-                let raw_key_right = re.replace_all(&event_key[..], "");
-                key = format!("{}_{}", key, raw_key_right.to_lowercase());
-            }
-            emap.insert(key, event);
-        }
-
-        emap
+        keys
     }
 }
 
@@ -491,13 +1216,22 @@ impl Event {
 
         Event { event_key, client }
     }
+    /// Fetch one of the event's endpoints from The Orange Alliance, which only knows its own
+    /// event keys.
+    fn fetch(&self, target: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if !self.client.backend().uses_toa_keys() {
+            return Err(format!(
+                "{} is only available from The Orange Alliance, and this client has another backend",
+                target
+            )
+            .into());
+        }
+        self.client.fetch(target)
+    }
     #[doc(hidden)]
     pub fn name(&self) -> String {
-        let resp = match self.client.request(&*format!("/event/{}", self.event_key)) {
-            Ok(r) => match r.text() {
-                Ok(t) => t,
-                Err(e) => panic!("Something went wrong: {}", e),
-            },
+        let resp = match self.fetch(&format!("/event/{}", self.event_key)) {
+            Ok(r) => r,
             Err(e) => panic!("Something went wrong: {}", e),
         };
 
@@ -528,15 +1262,10 @@ impl Event {
     /// - The HTTP request was not successful
     /// - The data received from the API was invalid JSON
     /// - The data received was in the wrong format
+    /// - The client's backend has its own event keys, and does not ask The Orange Alliance
     pub fn properties(&self) -> HashMap<String, String, RandomState> {
-        let resp = match self
-            .client
-            .request(&format!("/event/{}", self.event_key)[..])
-        {
-            Ok(r) => match r.text() {
-                Ok(t) => t,
-                Err(e) => panic!("Something went wrong: {}", e),
-            },
+        let resp = match self.fetch(&format!("/event/{}", self.event_key)[..]) {
+            Ok(r) => r,
             Err(e) => panic!("Something went wrong: {}", e),
         };
 
@@ -579,30 +1308,230 @@ impl Event {
 
         new_map
     }
+    /// Every match played at the event so far.
+    ///
+    /// The matches come from the client's [`Backend`](trait.Backend.html).
+    ///
+    /// # Panics
+    ///
+    /// This method can panic in the following ways:
+    /// - The HTTP request was not successful
+    /// - The data received from the API was invalid JSON
+    /// - The data received was in the wrong format
+    pub fn matches(&self) -> Vec<Match> {
+        match self.client.backend().matches(&self.client, &self.event_key) {
+            Ok(m) => m,
+            Err(e) => panic!("Something went wrong: {}", e),
+        }
+    }
+
+    /// Watch the event for new match results, ranking changes, alliances and awards.
+    ///
+    /// See [`watch::Watcher`](watch/struct.Watcher.html) for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How long to wait between polls while the event is changing.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the client has a [`Backend`](trait.Backend.html) other than
+    /// The Orange Alliance, which the watcher polls.
+    pub fn watch(&self, interval: std::time::Duration) -> watch::Watcher {
+        if !self.client.backend().uses_toa_keys() {
+            panic!("Something went wrong: only events of The Orange Alliance can be watched");
+        }
+        watch::Watcher::new(self.clone(), interval)
+    }
+
+    /// The season-specific score breakdown of every match at the event, as returned by the
+    /// API. Each object has a `match_key`, and the breakdown of each alliance under `red`
+    /// and `blue`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic in the following ways:
+    /// - The HTTP request was not successful
+    /// - The data received from the API was invalid JSON
+    /// - The data received was in the wrong format
+    /// - The client's backend has its own event keys, and does not ask The Orange Alliance
+    pub fn match_details(&self) -> Vec<serde_json::Value> {
+        let resp = match self.fetch(&format!("/event/{}/matches/details", self.event_key)) {
+            Ok(r) => r,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+
+        let json: serde_json::Value = match serde_json::from_str(&resp[..]) {
+            Ok(v) => v,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+
+        // Events without details get an error object instead of an empty list.
+        if json.get("_code").is_some() {
+            return Vec::new();
+        }
+        match json.as_array() {
+            Some(a) => a.clone(),
+            None => panic!("Something went wrong"),
+        }
+    }
+
+    /// How hard each team's qualification schedule has been so far, according to `model`.
+    ///
+    /// See [`stats::schedule_strength`](stats/fn.schedule_strength.html) for details.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the data sent by the API is in the wrong format.
+    pub fn schedule_strength(&self, model: &stats::Model) -> HashMap<u32, ScheduleStrength> {
+        let played: Vec<Match> = self
+            .matches()
+            .into_iter()
+            .filter(|m| m.is_qualification() && m.is_played())
+            .collect();
+        stats::schedule_strength(&played, model)
+    }
+
+    /// The qualification rankings of the event, best rank first.
+    ///
+    /// The rankings come from the client's [`Backend`](trait.Backend.html).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the data sent by the API is in the wrong format.
+    pub fn rankings(&self) -> Vec<Ranking> {
+        match self
+            .client
+            .backend()
+            .rankings(&self.client, &self.event_key)
+        {
+            Ok(r) => r,
+            Err(e) => panic!("Something went wrong: {}", e),
+        }
+    }
+
+    /// A pick list of the teams at this event, built from the rankings and the component
+    /// OPRs of the qualification matches played so far.
+    ///
+    /// See the [`picklist`](picklist/index.html) module to simulate the alliance selection
+    /// with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - How much each scoring period counts.
+    /// * `do_not_pick` - The numbers of the teams you do not want to pick.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the data sent by the API is in the wrong format.
+    pub fn pick_list(
+        &self,
+        weights: &picklist::Weights,
+        do_not_pick: &[u32],
+    ) -> Vec<picklist::Pick> {
+        picklist::pick_list(&self.rankings(), &self.stats(), weights, do_not_pick)
+    }
+
+    /// OPR, DPR, CCWM and the OPR of each scoring period, calculated from the
+    /// qualification matches played so far.
+    ///
+    /// Unlike [`opr`](#method.opr), this does not wait for The Orange Alliance to update its
+    /// numbers. See the [`stats`](stats/index.html) module to calculate them from your own
+    /// selection of matches.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the data sent by the API is in the wrong format.
+    pub fn stats(&self) -> HashMap<u32, TeamStats> {
+        let quals: Vec<Match> = self
+            .matches()
+            .into_iter()
+            .filter(|m| m.is_qualification() && m.is_played())
+            .collect();
+        stats::team_stats(&quals)
+    }
+    /// Simulate the remaining qualification matches `runs` times and return the
+    /// distribution of every team's final rank.
+    ///
+    /// The number of alliances is read from the event information, or taken to be four when
+    /// the client has a [`Backend`](trait.Backend.html) other than The Orange Alliance, and the
+    /// size of the alliances and the ranking rules from the season of the event. See
+    /// [`simulation::simulate_rankings`](simulation/fn.simulate_rankings.html) for details.
+    ///
+    /// ```no_run
+    /// use rustoa::stats::{self, Model};
+    ///
+    /// # let client = rustoa::Client::new("api_key");
+    /// let event = client.event("1920-TX-TRQ");
+    /// let mut elo = rustoa::Elo::new();
+    /// elo.replay(&event.matches());
+    /// let ranks = event.simulate_rankings(10000, &Model::Elo { elo: &elo, spread: 40.0 });
+    /// println!("Top four: {:.0}%", ranks[&16405].top_four * 100.0);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the data sent by the API is in the wrong format.
+    pub fn simulate_rankings(
+        &self,
+        runs: u32,
+        model: &stats::Model,
+    ) -> HashMap<u32, RankDistribution> {
+        let quals: Vec<Match> = self
+            .matches()
+            .into_iter()
+            .filter(|m| m.is_qualification())
+            .collect();
+        // Only The Orange Alliance says how many alliances an event has.
+        let alliance_count = if self.client.backend().uses_toa_keys() {
+            match self.properties().get("alliance_count") {
+                Some(n) => n.parse::<u32>().unwrap_or(4),
+                None => 4,
+            }
+        } else {
+            4
+        };
+        let seed = match SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            Ok(d) => d.as_nanos() as u64,
+            Err(_) => 0,
+        };
+        let season = self.event_key.get(..4).and_then(|s| s.parse::<i32>().ok());
+        let rules = simulation::RankingRules::for_season_key(season.unwrap_or(0));
+        let mut teams: Vec<u32> = quals
+            .iter()
+            .flat_map(|m| m.red_teams.iter().chain(m.blue_teams.iter()))
+            .cloned()
+            .collect();
+        teams.sort();
+        teams.dedup();
+        let alliance_size = simulation::alliance_size(season.unwrap_or(0), teams.len());
+        simulation::simulate_rankings(
+            &quals,
+            runs,
+            model,
+            alliance_count,
+            alliance_size,
+            rules,
+            seed,
+        )
+    }
     fn get_rankings_data(
         &self,
         team_number: u32,
-        query: &str,
+        query: &'static str,
     ) -> Result<f64, Box<dyn std::error::Error>> {
-        let resp = self
+        if self.client.backend().missing_fields().contains(&query) {
+            return Err(Box::new(BackendError::Unsupported(query)));
+        }
+        let rankings = self
             .client
-            .request(&*format!("/event/{}/rankings", self.event_key))?;
-        let map: serde_json::Value = serde_json::from_str(&*resp.text()?)?;
-        let arr = match map.as_array() {
-            Some(a) => a,
-            None => panic!("Something went wrong"),
-        };
-        for val in arr.iter() {
-            let num = &val["team"]["team_number"];
-            let num = match num.as_f64() {
-                Some(n) => n as u32,
-                None => panic!("Something went wrong"),
-            };
-            if num == team_number {
-                match &val[query].as_f64() {
-                    Some(n) => return Ok(n.clone()),
-                    None => continue,
-                };
+            .backend()
+            .rankings(&self.client, &self.event_key)?;
+        for ranking in rankings.iter() {
+            if ranking.team_number == team_number {
+                if let Some(n) = ranking.field(query) {
+                    return Ok(n);
+                }
             }
         }
         panic!("Something went wrong");
@@ -632,7 +1561,8 @@ impl Event {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API is in the wrong format.
+    /// This method will panic if the data sent by the API is in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn rank_change(&self, team_number: u32) -> f64 {
         let resp = match self.get_rankings_data(team_number, "rank_change") {
             Ok(o) => o,
@@ -696,7 +1626,8 @@ impl Event {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API is in the wrong format.
+    /// This method will panic if the data sent by the API is in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn opr(&self, team_number: u32) -> f64 {
         let resp = match self.get_rankings_data(team_number, "opr") {
             Ok(o) => o,
@@ -712,7 +1643,8 @@ impl Event {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API is in the wrong format.
+    /// This method will panic if the data sent by the API is in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn np_opr(&self, team_number: u32) -> f64 {
         let resp = match self.get_rankings_data(team_number, "np_opr") {
             Ok(o) => o,
@@ -728,7 +1660,8 @@ impl Event {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API is in the wrong format.
+    /// This method will panic if the data sent by the API is in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn highest_qualifier_score(&self, team_number: u32) -> f64 {
         let resp = match self.get_rankings_data(team_number, "highest_qual_score") {
             Ok(o) => o,
@@ -760,7 +1693,8 @@ impl Event {
     ///
     /// # Panics
     ///
-    /// This method will panic if the data sent by the API is in the wrong format.
+    /// This method will panic if the data sent by the API is in the wrong format, or if the
+    /// client's [`Backend`](trait.Backend.html) does not provide it.
     pub fn qualifying_points(&self, team_number: u32) -> f64 {
         let resp = match self.get_rankings_data(team_number, "qualifying_points") {
             Ok(o) => o,
@@ -798,6 +1732,11 @@ impl Event {
 /// let wins = team.season_wins(rustoa::Season::SkyStone);
 /// ```
 pub enum Season {
+    IntoTheDeep,
+    CenterStage,
+    PowerPlay,
+    FreightFrenzy,
+    UltimateGoal,
     SkyStone,
     RoverRuckus,
     RelicRecovery,
@@ -808,6 +1747,11 @@ impl Season {
     #[doc(hidden)]
     pub fn value(&self) -> i32 {
         match self {
+            Season::IntoTheDeep => 2425,
+            Season::CenterStage => 2324,
+            Season::PowerPlay => 2223,
+            Season::FreightFrenzy => 2122,
+            Season::UltimateGoal => 2021,
             Season::SkyStone => 1920,
             Season::RoverRuckus => 1819,
             Season::RelicRecovery => 1718,
@@ -817,6 +1761,11 @@ impl Season {
     #[doc(hidden)]
     pub fn value_of(s: String) -> Season {
         match &s[..] {
+            "2425" => Season::IntoTheDeep,
+            "2324" => Season::CenterStage,
+            "2223" => Season::PowerPlay,
+            "2122" => Season::FreightFrenzy,
+            "2021" => Season::UltimateGoal,
             "1920" => Season::SkyStone,
             "1819" => Season::RoverRuckus,
             "1718" => Season::RelicRecovery,
@@ -829,6 +1778,11 @@ impl Season {
 impl std::fmt::Display for Season {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Season::IntoTheDeep => write!(f, "Season::IntoTheDeep"),
+            Season::CenterStage => write!(f, "Season::CenterStage"),
+            Season::PowerPlay => write!(f, "Season::PowerPlay"),
+            Season::FreightFrenzy => write!(f, "Season::FreightFrenzy"),
+            Season::UltimateGoal => write!(f, "Season::UltimateGoal"),
             Season::SkyStone => write!(f, "Season::SkyStone"),
             Season::RoverRuckus => write!(f, "Season::RoverRuckus"),
             Season::RelicRecovery => write!(f, "Season::RelicRecovery"),
@@ -839,30 +1793,38 @@ impl std::fmt::Display for Season {
 
 #[cfg(test)]
 mod tests {
-    fn create_client() -> super::Client {
-        let key = match std::env::var("API_KEY") {
-            Ok(k) => k,
-            Err(e) => panic!("Something went wrong: {}", e),
-        };
-        super::Client::new(&*key)
+    use crate::test_server::{fixture_file, fixture_paths, fixtures_dir, recorded_dir, TestServer};
+
+    fn create_client() -> (TestServer, super::Client) {
+        let server = TestServer::toa();
+        // Pooled connections would keep talking to the server after it is dropped.
+        let http = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        let client = super::Client::new("api_key")
+            .with_base_url(server.url())
+            .with_transport(super::ReqwestTransport::with_client(http));
+        (server, client)
     }
     #[test]
     fn correct_version() {
-        let client = create_client();
+        let (_server, client) = create_client();
         assert_eq!("3.7.0", client.api_version());
     }
     #[test]
     fn check_number() {
-        let client = create_client();
+        let (_server, client) = create_client();
         let team = client.team(16405);
         assert_eq!(team.team_number, 16405);
     }
     #[test]
     fn check_compat() {
-        let client = create_client();
+        let (_server, client) = create_client();
         let team1 = client.team(16405);
         let team2 = client.team(16405);
         assert_eq!(team1.wins(), team2.wins());
+        assert_eq!(team1.wins(), 6);
         let year1 = match team1.properties().get("rookie_year") {
             Some(y) => y.clone(),
             None => panic!("Something went wrong"),
@@ -891,14 +1853,14 @@ mod tests {
     }
     #[test]
     fn check_numbers() {
-        let client = create_client();
+        let (_server, client) = create_client();
         let team1 = client.team(16405);
         let team2 = client.team(16405);
         assert_eq!(team1.team_number, team2.team_number);
     }
     #[test]
     fn test_property() {
-        let client = create_client();
+        let (_server, client) = create_client();
         let team = client.team(16405);
         let year = match team.properties().get("rookie_year") {
             Some(y) => y.clone(),
@@ -907,6 +1869,338 @@ mod tests {
         assert_eq!("2019", year);
     }
 
+    #[test]
+    fn cache_is_shared_between_clones() {
+        use std::time::SystemTime;
+        let client = super::Client::new("api_key").with_cache(super::CachePolicy::default());
+        let team = client.team(16405);
+        let cache = team.client.inner.cache.as_ref().unwrap();
+        cache.lock().unwrap().insert(
+            "/",
+            r#"{"version": "3.7.0"}"#.to_string(),
+            SystemTime::now(),
+        );
+        assert_eq!("3.7.0", client.api_version());
+        client.invalidate("/");
+        assert!(cache.lock().unwrap().get("/").is_none());
+    }
+
+    #[test]
+    fn clones_share_state_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<super::Client>();
+        assert_send_sync::<super::Team>();
+        assert_send_sync::<super::Event>();
+
+        let client = super::Client::new("api_key")
+            .with_transport(
+                super::FixtureTransport::new()
+                    .with("/", r#"{"version": "3.7.0"}"#)
+                    .with_status("/team/1/", 429, ""),
+            )
+            .with_cache(super::CachePolicy::default())
+            .with_rate_limit(2, std::time::Duration::from_millis(100));
+        assert_eq!(client.api_version(), "3.7.0");
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                std::thread::spawn(move || client.team(16405).client.api_version())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "3.7.0");
+        }
+        client.request("/team/1/").unwrap();
+        client.request("/team/1/").unwrap();
+
+        let metrics = client.metrics();
+        assert_eq!(metrics.requests, 3);
+        assert_eq!(metrics.cache_hits, 4);
+        assert_eq!((metrics.rate_limited, metrics.errors), (2, 2));
+        assert_eq!(metrics.throttled, 1);
+
+        // A reconfigured clone still counts towards the same metrics.
+        let other = client.clone().with_base_url("http://127.0.0.1:1");
+        other.request("/").unwrap();
+        assert_eq!(client.metrics().requests, 4);
+    }
+
+    #[test]
+    fn cache_avoids_repeat_requests() {
+        let (server, client) = create_client();
+        let team = client.with_cache(super::CachePolicy::default()).team(16405);
+        assert_eq!(team.wins(), 6);
+        assert_eq!(team.losses(), 4);
+        assert_eq!(server.hits("/team/16405/wlt"), 1);
+    }
+
+    #[test]
+    fn disk_cache_serves_stale_responses_offline() {
+        let dir = std::env::temp_dir().join(format!("rustoa-offline-{}", std::process::id()));
+        let disk = super::DiskCache::open(&dir).unwrap();
+        let (server, client) = create_client();
+        let client = client.with_disk_cache(disk);
+        client.prewarm(&["1920-TX-TRQ"]).unwrap();
+        assert_eq!(server.hits("/event/1920-TX-TRQ/rankings"), 1);
+        drop(server);
+
+        let event = client.event("1920-TX-TRQ");
+        assert_eq!(event.name(), "Trinity River Qualifier");
+        assert!(client.is_stale("/event/1920-TX-TRQ"));
+        assert!(!client.is_stale("/event/1920-TX-TRQ/rankings"));
+        event.rank(16405);
+        assert!(client.is_stale("/event/1920-TX-TRQ/rankings"));
+        assert!(client.raw("/team/16405/wlt").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rate_limited_responses_fall_back_to_the_disk_cache() {
+        use super::FixtureTransport;
+
+        let dir = std::env::temp_dir().join(format!("rustoa-429-{}", std::process::id()));
+        let disk = super::DiskCache::open(&dir).unwrap();
+        disk.put("/team/16405/wlt", "[]").unwrap();
+        let client = super::Client::new("")
+            .with_transport(FixtureTransport::new().with_status("/team/16405/wlt", 429, "{}"))
+            .with_disk_cache(disk.clone());
+        let resp = client.raw("/team/16405/wlt").unwrap();
+        assert!(resp.stale);
+        assert_eq!(resp.body, "[]");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // A response that cannot be stored is still returned.
+        let client = super::Client::new("")
+            .with_transport(FixtureTransport::new().with("/", "{}"))
+            .with_disk_cache(disk);
+        assert_eq!(client.raw("/").unwrap().body, "{}");
+    }
+
+    #[test]
+    fn rankings_without_opr_can_still_be_read() {
+        use super::FixtureTransport;
+
+        let transport = FixtureTransport::new().with(
+            "/event/1920-TX-TRQ/rankings",
+            r#"[{"team_key": "16405", "rank": 2, "wins": 4, "losses": 1, "ties": 0,
+                "opr": null, "np_opr": null, "highest_qual_score": null, "ranking_points": 80,
+                "qualifying_points": 8, "tie_breaker_points": 0}]"#,
+        );
+        let client = super::Client::new("").with_transport(transport);
+        let event = client.event("1920-TX-TRQ");
+        assert_eq!(event.rank(16405), 2.0);
+        assert_eq!(event.rankings()[0].opr, None);
+    }
+
+    #[test]
+    fn events_without_details_have_none() {
+        use super::FixtureTransport;
+
+        let transport = FixtureTransport::new().with_status(
+            "/event/1920-TX-TRQ/matches/details",
+            404,
+            r#"{"_code": 404, "_message": "Content not found."}"#,
+        );
+        let client = super::Client::new("").with_transport(transport);
+        assert!(client.event("1920-TX-TRQ").match_details().is_empty());
+    }
+
+    #[test]
+    fn custom_transport_receives_headers() {
+        use super::{Transport, TransportRequest, TransportResponse};
+        use std::sync::Mutex;
+
+        #[derive(Debug, Default)]
+        struct Recording(Mutex<Vec<TransportRequest>>);
+        impl Transport for Recording {
+            fn get(
+                &self,
+                request: &TransportRequest,
+            ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+                self.0.lock().unwrap().push(request.clone());
+                Ok(TransportResponse {
+                    status: 200,
+                    body: r#"{"version": "3.7.0"}"#.to_string(),
+                    headers: Vec::new(),
+                })
+            }
+        }
+
+        let transport = std::sync::Arc::new(Recording::default());
+        let client = super::Client::new("api_key").with_transport(transport.clone());
+        assert_eq!("3.7.0", client.api_version());
+        let requests = transport.0.lock().unwrap();
+        assert_eq!(requests[0].url, "https://theorangealliance.org/api/");
+        assert_eq!(requests[0].path, "/");
+        assert!(requests[0]
+            .headers
+            .contains(&("X-TOA-Key".to_string(), "api_key".to_string())));
+    }
+
+    #[test]
+    fn stats_from_fixture_matches() {
+        let (_server, client) = create_client();
+        let event = client.event("1920-TX-TRQ");
+        let matches = event.matches();
+        assert_eq!(matches[0].red_teams, vec![17010, 8565]);
+        assert_eq!(matches[0].blue_teams, vec![16405, 12456]);
+
+        let stats = event.stats();
+        let team = &stats[&16405];
+        let played = matches
+            .iter()
+            .filter(|m| m.is_qualification() && m.has_team(16405))
+            .count();
+        assert_eq!(team.matches_played as usize, played);
+        assert!((team.ccwm - (team.opr - team.dpr)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ratings_from_fixture_events() {
+        let (_server, client) = create_client();
+        let events = vec![client.event("1920-TX-TRQ"), client.event("1920-TX-PLQ")];
+        let mut elo = super::Elo::new();
+        elo.replay_events(&events);
+        let history = elo.history(16405);
+        assert_eq!(history[0].event_key, "1920-TX-TRQ");
+        assert_eq!(history.last().unwrap().rating, elo.rating(16405));
+    }
+
+    #[test]
+    fn backtest_fixture_matches() {
+        use super::stats::{backtest, Backtest};
+        let (_server, client) = create_client();
+        let quals: Vec<super::Match> = client
+            .event("1920-TX-TRQ")
+            .matches()
+            .into_iter()
+            .filter(|m| m.is_qualification())
+            .collect();
+        let elo = backtest(&quals, Backtest::Elo(super::Elo::new()));
+        assert_eq!(elo.matches as usize, quals.len());
+        let opr = backtest(&quals, Backtest::Opr);
+        assert!(opr.matches < elo.matches);
+        assert!(opr.brier_score >= 0.0 && opr.brier_score <= 1.0);
+    }
+
+    #[test]
+    fn simulate_fixture_event() {
+        let (_server, client) = create_client();
+        let event = client.event("1920-TX-TRQ");
+        let elo = super::Elo::new();
+        let model = super::stats::Model::Elo {
+            elo: &elo,
+            spread: 40.0,
+        };
+        let ranks = event.simulate_rankings(20, &model);
+        // Every fixture match has been played, so the ranking is the same in every run.
+        let first = ranks.values().find(|r| r.rank_counts[0] > 0).unwrap();
+        assert_eq!(first.rank_counts[0], 20);
+        let picked: f64 = ranks.values().map(|r| r.picked).sum();
+        assert_eq!(picked, 8.0);
+    }
+
+    #[test]
+    fn pick_list_from_fixture_event() {
+        use super::picklist::{availability, SelectionRules};
+        let (_server, client) = create_client();
+        let event = client.event("1920-TX-TRQ");
+        let rankings = event.rankings();
+        assert_eq!(rankings[0].team_number, 11260);
+        assert_eq!(rankings[0].rank, 1);
+
+        let picks = event.pick_list(&Default::default(), &[11260]);
+        assert!(picks.last().unwrap().do_not_pick);
+        let rules = SelectionRules::new(4, 3).noise(5.0);
+        let available = availability(&rankings, &picks, &rules, 11260, 50, 3);
+        assert!(available.iter().all(|(_, p)| *p == 1.0));
+    }
+
+    #[test]
+    fn season_report_from_fixture_results() {
+        let (_server, client) = create_client();
+        let report = client.team(16405).season_report(super::Season::SkyStone);
+        assert_eq!(report.events.len(), 2);
+        assert_eq!(report.events[0].event_name, "Trinity River Qualifier");
+        assert_eq!(report.events[0].awards, vec!["Inspire Award 2nd Place"]);
+        assert_eq!(report.events[1].ranking.team_number, 16405);
+        assert_eq!(report.max_opr(), Some(57.91));
+        assert_eq!(report.best_rank(), Some(2));
+        assert_eq!((report.wins(), report.losses()), (6, 4));
+        assert!(!report.advanced());
+    }
+
+    #[test]
+    fn head_to_head_from_fixture_events() {
+        let (_server, client) = create_client();
+        let history = client
+            .team(16405)
+            .head_to_head(8565, &[super::Season::SkyStone]);
+        assert_eq!(history.as_partners.match_keys, vec!["1920-TX-PLQ-Q006-1"]);
+        assert_eq!(history.as_partners.average_combined_score, 140.0);
+        assert_eq!(history.as_opponents.matches(), 6);
+        assert_eq!(history.as_opponents.win_rate(), 0.5);
+        assert_eq!(history.as_opponents.average_combined_score, 214.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "only available from The Orange Alliance")]
+    fn head_to_head_needs_toa_event_keys() {
+        let client = super::Client::new("api_key")
+            .with_backend(super::backend::FtcEvents::new("user", "token"));
+        client
+            .team(16405)
+            .head_to_head(8565, &[super::Season::SkyStone]);
+    }
+
+    #[test]
+    fn match_by_key() {
+        let (_server, client) = create_client();
+        let m = client.match_result("1920-TX-TRQ-Q001-1");
+        assert_eq!(m.red_teams, vec![17010, 8565]);
+        assert_eq!((m.red_score, m.blue_score), (107, 108));
+    }
+
+    #[test]
+    fn event_list_from_one_season_listing() {
+        let read =
+            |path: &str| std::fs::read_to_string(fixture_file(&fixtures_dir(), path)).unwrap();
+        let listing: Vec<serde_json::Value> = ["/event/1920-TX-PLQ", "/event/1920-TX-TRQ"]
+            .iter()
+            .map(|p| serde_json::from_str::<serde_json::Value>(&read(p)).unwrap()[0].clone())
+            .collect();
+        // No `/event/{key}` responses are in the fixtures, so any per-event request would fail.
+        let client = super::Client::new("api_key").with_transport(
+            super::FixtureTransport::new()
+                .with("/team/16405/events/1920", &read("/team/16405/events/1920"))
+                .with(
+                    "/team/16405/results/1920",
+                    &read("/team/16405/results/1920"),
+                )
+                .with("/team/16405/awards/1920", &read("/team/16405/awards/1920"))
+                .with(
+                    "/event?season_key=1920",
+                    &serde_json::Value::from(listing).to_string(),
+                ),
+        );
+
+        let events = client.team(16405).event_list(super::Season::SkyStone);
+        assert_eq!(events.keys(), vec!["1920-TX-TRQ", "1920-TX-PLQ"]);
+        assert_eq!(events[0].start_date, "2019-12-07T00:00:00.000Z");
+        let trq = events.find_by_slug("trinity_river_qualifier");
+        assert_eq!(trq.len(), 1);
+        assert_eq!(trq[0].event_key, "1920-TX-TRQ");
+        assert_eq!(
+            events.find_by_name("TRINITY RIVER QUALIFIER"),
+            vec![events.get("1920-TX-TRQ").unwrap()]
+        );
+        assert!(events.get("1920-TX-NTXC").is_none());
+
+        let report = client.team(16405).season_report(super::Season::SkyStone);
+        assert_eq!(report.events[0].event_name, "Trinity River Qualifier");
+        assert_eq!(report.events[1].event_key, "1920-TX-PLQ");
+    }
+
     #[test]
     fn test_season() {
         let season = super::Season::SkyStone;
@@ -915,7 +2209,7 @@ mod tests {
 
     #[test]
     fn test_event() {
-        let client = create_client();
+        let (_server, client) = create_client();
         let team = client.team(16405);
         let event = match team
             .events(super::Season::SkyStone)
@@ -931,4 +2225,30 @@ mod tests {
         };
         assert_eq!(name1, name2);
     }
+
+    /// Record the live response for every fixture path, to compare with the synthetic ones.
+    ///
+    /// Run with `API_KEY=... cargo test record_fixtures -- --ignored`. The responses are
+    /// written to `tests/fixtures/toa-recorded`, which the other tests do not read.
+    #[test]
+    #[ignore]
+    fn record_fixtures() {
+        let key = match std::env::var("API_KEY") {
+            Ok(k) => k,
+            Err(e) => panic!("Something went wrong: {}", e),
+        };
+        let client = super::Client::new(&key);
+        let recorded = recorded_dir();
+        for path in fixture_paths(&fixtures_dir()) {
+            let body = match client.raw(&path) {
+                Ok(r) => r.body,
+                Err(e) => panic!("Something went wrong: {}", e),
+            };
+            let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+            let pretty = serde_json::to_string_pretty(&json).unwrap();
+            let file = fixture_file(&recorded, &path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, pretty + "\n").unwrap();
+        }
+    }
 }
@@ -0,0 +1,69 @@
+//! Client-side rate limiting.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Allows at most `requests` requests in any window of length `per`.
+///
+/// It is shared by every clone of a [`Client`](../struct.Client.html), so threads using the
+/// same client take turns instead of each getting the full limit.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    requests: usize,
+    per: Duration,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests: u32, per: Duration) -> RateLimiter {
+        RateLimiter {
+            requests: requests.max(1) as usize,
+            per,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Wait until another request may be sent, and record it.
+    ///
+    /// It returns whether the caller had to wait.
+    pub(crate) fn acquire(&self) -> bool {
+        let mut waited = false;
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().unwrap();
+                let now = Instant::now();
+                while sent
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= self.per)
+                {
+                    sent.pop_front();
+                }
+                if sent.len() < self.requests {
+                    sent.push_back(now);
+                    return waited;
+                }
+                self.per - now.duration_since(sent[0])
+            };
+            waited = true;
+            thread::sleep(wait);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn waits_for_the_window_to_pass() {
+        let limiter = RateLimiter::new(2, Duration::from_millis(200));
+        let start = Instant::now();
+        assert!(!limiter.acquire());
+        assert!(!limiter.acquire());
+        assert!(limiter.acquire());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{Client, Team};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How many teams [`Client::teams_bulk`](struct.Client.html#method.teams_bulk) looks up at
+/// once.
+pub(crate) const BULK_WORKERS: usize = 4;
+
+/// Why a team could not be looked up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeamLookupError {
+    /// The API has no team with this number.
+    NotFound(u32),
+    /// The API answered `429 Too Many Requests`, even after waiting.
+    RateLimited(u32),
+    /// The API answered with another unsuccessful status code.
+    Api { team_number: u32, status: u16 },
+    /// The API could not be reached.
+    Transport { team_number: u32, message: String },
+    /// The API returned invalid JSON.
+    Json { team_number: u32, message: String },
+}
+
+impl TeamLookupError {
+    /// The team number that was looked up.
+    pub fn team_number(&self) -> u32 {
+        match self {
+            TeamLookupError::NotFound(n) | TeamLookupError::RateLimited(n) => *n,
+            TeamLookupError::Api { team_number, .. }
+            | TeamLookupError::Transport { team_number, .. }
+            | TeamLookupError::Json { team_number, .. } => *team_number,
+        }
+    }
+
+    /// Whether the team does not exist, as opposed to the lookup failing.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, TeamLookupError::NotFound(_))
+    }
+}
+
+impl fmt::Display for TeamLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TeamLookupError::NotFound(n) => write!(f, "Team {} was not found", n),
+            TeamLookupError::RateLimited(n) => {
+                write!(f, "The API rate limit was reached looking up team {}", n)
+            }
+            TeamLookupError::Api {
+                team_number,
+                status,
+            } => write!(
+                f,
+                "The API returned {} looking up team {}",
+                status, team_number
+            ),
+            TeamLookupError::Transport {
+                team_number,
+                message,
+            } => write!(
+                f,
+                "The API could not be reached looking up team {}: {}",
+                team_number, message
+            ),
+            TeamLookupError::Json {
+                team_number,
+                message,
+            } => write!(
+                f,
+                "The API returned invalid JSON for team {}: {}",
+                team_number, message
+            ),
+        }
+    }
+}
+
+impl Error for TeamLookupError {}
+
+/// The outcome of asking the API for a single team.
+enum Lookup {
+    Found,
+    /// The API is rate limiting, and may have said how long to wait.
+    RateLimited(Option<Duration>),
+    Failed(TeamLookupError),
+}
+
+fn lookup(client: &Client, team_number: u32) -> Lookup {
+    let path = format!("/team/{}/", team_number);
+    let (body, live) = match client.cached(&path) {
+        Some(cached) => (cached.body, false),
+        None => {
+            let resp = match client.request(&path) {
+                Ok(r) => r,
+                Err(e) => {
+                    return Lookup::Failed(TeamLookupError::Transport {
+                        team_number,
+                        message: e.to_string(),
+                    })
+                }
+            };
+            match resp.status {
+                404 => return Lookup::Failed(TeamLookupError::NotFound(team_number)),
+                429 => return Lookup::RateLimited(resp.retry_after()),
+                _ if !resp.is_success() => {
+                    return Lookup::Failed(TeamLookupError::Api {
+                        team_number,
+                        status: resp.status,
+                    })
+                }
+                _ => {}
+            }
+            (resp.body, true)
+        }
+    };
+    let json: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            return Lookup::Failed(TeamLookupError::Json {
+                team_number,
+                message: e.to_string(),
+            })
+        }
+    };
+    // The API answers an unknown team with an empty list rather than a 404 on some routes.
+    match json.as_array() {
+        Some(teams) if !teams.is_empty() => {
+            if live {
+                client.remember(&path, &body, SystemTime::now());
+            }
+            Lookup::Found
+        }
+        _ => Lookup::Failed(TeamLookupError::NotFound(team_number)),
+    }
+}
+
+/// Look up one team, retrying once after a pause if the API is rate limiting.
+///
+/// The pause is as long as the API asks for, or the client's
+/// [retry pause](struct.Client.html#method.with_retry_pause) if it does not say.
+/// `paused_until` is shared between every worker of a bulk lookup, so a 429 seen by one of
+/// them makes all of them wait.
+fn checked(
+    client: &Client,
+    team_number: u32,
+    paused_until: &Mutex<Option<Instant>>,
+) -> Result<(), TeamLookupError> {
+    for _ in 0..2 {
+        let until = *paused_until.lock().unwrap();
+        if let Some(until) = until {
+            let now = Instant::now();
+            if until > now {
+                thread::sleep(until - now);
+            }
+        }
+        match lookup(client, team_number) {
+            Lookup::Found => return Ok(()),
+            Lookup::Failed(e) => return Err(e),
+            Lookup::RateLimited(retry_after) => {
+                let mut until = paused_until.lock().unwrap();
+                let resume = Instant::now() + retry_after.unwrap_or_else(|| client.retry_pause());
+                if until.is_none_or(|u| u < resume) {
+                    *until = Some(resume);
+                }
+            }
+        }
+    }
+    Err(TeamLookupError::RateLimited(team_number))
+}
+
+pub(crate) fn team_checked(client: &Client, team_number: u32) -> Result<Team, TeamLookupError> {
+    checked(client, team_number, &Mutex::new(None))?;
+    Ok(client.team(team_number))
+}
+
+pub(crate) fn teams_bulk(
+    client: &Client,
+    team_numbers: &[u32],
+    workers: usize,
+) -> Vec<Result<Team, TeamLookupError>> {
+    // Each team is only looked up once, however many times it is listed.
+    let mut unique: Vec<u32> = Vec::new();
+    for n in team_numbers.iter() {
+        if !unique.contains(n) {
+            unique.push(*n);
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let paused_until = Mutex::new(None);
+    let results: Mutex<HashMap<u32, Result<(), TeamLookupError>>> = Mutex::new(HashMap::new());
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(unique.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let team_number = match unique.get(i) {
+                    Some(n) => *n,
+                    None => break,
+                };
+                let result = checked(client, team_number, &paused_until);
+                results.lock().unwrap().insert(team_number, result);
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    team_numbers
+        .iter()
+        .map(|n| match &results[n] {
+            Ok(()) => Ok(client.team(*n)),
+            Err(e) => Err(e.clone()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TeamLookupError;
+    use crate::{CachePolicy, Client, FixtureTransport, TransportResponse};
+    use std::time::{Duration, Instant};
+
+    fn client() -> Client {
+        let team = r#"[{"team_key":"16405","team_number":16405}]"#;
+        let asks_to_wait = TransportResponse {
+            status: 429,
+            body: String::new(),
+            headers: vec![("retry-after".to_string(), "0.05".to_string())],
+        };
+        Client::new("api_key")
+            .with_transport(
+                FixtureTransport::new()
+                    .with("/team/16405/", team)
+                    .with("/team/8565/", r#"[{"team_key":"8565","team_number":8565}]"#)
+                    .with_status("/team/99999/", 404, r#"{"_code":404}"#)
+                    .with("/team/12/", "[]")
+                    .with_status("/team/500/", 503, "")
+                    .with_status("/team/429/", 429, "")
+                    .with_response("/team/430/", asks_to_wait),
+            )
+            .with_retry_pause(Duration::from_millis(20))
+    }
+
+    #[test]
+    fn checks_that_teams_exist() {
+        let client = client();
+        assert_eq!(client.team_checked(16405).unwrap().team_number, 16405);
+        assert_eq!(
+            client.team_checked(99999).err(),
+            Some(TeamLookupError::NotFound(99999))
+        );
+        assert!(client.team_checked(12).err().unwrap().is_not_found());
+        assert_eq!(
+            client.team_checked(500).err().unwrap().to_string(),
+            "The API returned 503 looking up team 500"
+        );
+        assert!(matches!(
+            client.team_checked(1),
+            Err(TeamLookupError::Transport { team_number: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn uses_the_cache() {
+        let client = client().with_cache(CachePolicy::default());
+        client.team_checked(16405).unwrap();
+        client.team_checked(16405).unwrap();
+        assert_eq!(client.metrics().requests, 1);
+        assert_eq!(client.metrics().cache_hits, 1);
+    }
+
+    #[test]
+    fn waits_as_long_as_the_api_asks() {
+        // The API asks for less than the retry pause.
+        let client = client().with_retry_pause(Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(
+            client.team_checked(430).err(),
+            Some(TeamLookupError::RateLimited(430))
+        );
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50) && elapsed < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn looks_up_teams_in_bulk() {
+        let client = client();
+        let start = Instant::now();
+        let results = client.teams_bulk(&[16405, 99999, 8565, 429, 12, 16405]);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        // 16405 is only looked up once, and 429 twice.
+        assert_eq!(client.metrics().requests, 6);
+
+        let found: Vec<Option<u32>> = results
+            .iter()
+            .map(|r| r.as_ref().ok().map(|t| t.team_number))
+            .collect();
+        assert_eq!(
+            found,
+            vec![Some(16405), None, Some(8565), None, None, Some(16405)]
+        );
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Team 99999 was not found",
+                "The API rate limit was reached looking up team 429",
+                "Team 12 was not found"
+            ]
+        );
+    }
+}
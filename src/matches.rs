@@ -0,0 +1,195 @@
+//! Match results.
+
+/// The result of a single match at an event.
+///
+/// You can get the matches of an event with [`Event::matches`](struct.Event.html#method.matches).
+///
+/// The scores of each alliance include the penalty points it was awarded for fouls by the
+/// other alliance. A match that has not been [played](#method.is_played) yet has no scores,
+/// and every score is `0` until it has; leave such matches out of anything that uses them.
+/// The Arrow, CSV and SQLite exports leave its scores empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    /// The key of the match, such as `1920-TX-TRQ-Q001-1`.
+    pub match_key: String,
+    /// The key of the event the match was played at, such as `1920-TX-TRQ`.
+    pub event_key: String,
+    /// When the match was scheduled, in RFC 3339 format.
+    pub scheduled_time: Option<String>,
+    /// When the match started, in RFC 3339 format. This is `None` for matches that have
+    /// not been played yet.
+    pub match_start_time: Option<String>,
+    /// The name of the match, such as `Quals 1`.
+    pub match_name: String,
+    /// The tournament level of the match. Qualification matches have a level of `1`, the two
+    /// semifinal series `21` and `22`, and the finals `4`.
+    pub tournament_level: u32,
+    /// The numbers of the teams on the red alliance.
+    pub red_teams: Vec<u32>,
+    /// The numbers of the teams on the blue alliance.
+    pub blue_teams: Vec<u32>,
+    pub red_score: i32,
+    pub blue_score: i32,
+    /// The penalty points the red alliance was awarded.
+    pub red_penalty: i32,
+    /// The penalty points the blue alliance was awarded.
+    pub blue_penalty: i32,
+    pub red_auto_score: i32,
+    pub blue_auto_score: i32,
+    pub red_tele_score: i32,
+    pub blue_tele_score: i32,
+    pub red_end_score: i32,
+    pub blue_end_score: i32,
+}
+
+impl Match {
+    /// Whether this is a qualification match.
+    pub fn is_qualification(&self) -> bool {
+        self.tournament_level == 1
+    }
+
+    /// Whether the match has been played.
+    pub fn is_played(&self) -> bool {
+        self.match_start_time.is_some()
+    }
+
+    /// Whether the team played in this match.
+    pub fn has_team(&self, team_number: u32) -> bool {
+        self.red_teams.contains(&team_number) || self.blue_teams.contains(&team_number)
+    }
+
+    /// The round of the match and its number within its series, read from its key and
+    /// tournament level, or `None` if they do not follow TOA's scheme.
+    pub(crate) fn round(&self) -> Option<(Round, u32)> {
+        let code = self.match_key.rsplit('-').nth(1)?;
+        let digits = code.get(1..)?;
+        match (code.get(..1)?, self.tournament_level) {
+            ("Q", 1) => Some((Round::Qualification, digits.parse().ok()?)),
+            ("E", level @ 21..=29) => {
+                Some((Round::Semifinal(level - 20), digits.get(1..)?.parse().ok()?))
+            }
+            ("F", 4) => Some((Round::Final, digits.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    /// A key that sorts matches in the order they were played: by the time they were
+    /// scheduled (or started, if they have no schedule), then by stage, then by key.
+    ///
+    /// Finals have a lower tournament level than semifinals, so the level is mapped to the
+    /// order of the stages first.
+    pub(crate) fn chronological_key(&self) -> (Option<&str>, u32, &str) {
+        let stage = match self.tournament_level {
+            1 => 0,
+            21..=29 => 1,
+            4 => 2,
+            level => level,
+        };
+        let time = self
+            .scheduled_time
+            .as_ref()
+            .or(self.match_start_time.as_ref());
+        (time.map(|t| &t[..]), stage, &self.match_key)
+    }
+
+    pub(crate) fn from_json(json: &serde_json::Value) -> Option<Match> {
+        let score = |key: &str| json[key].as_i64().map(|n| n as i32);
+
+        let mut red_teams = Vec::new();
+        let mut blue_teams = Vec::new();
+        for participant in json["participants"].as_array()?.iter() {
+            let team = participant["team_key"].as_str()?.parse::<u32>().ok()?;
+            match participant["station"].as_u64()? / 10 {
+                1 => red_teams.push(team),
+                2 => blue_teams.push(team),
+                _ => continue,
+            }
+        }
+
+        // Scores are null until a match has been played.
+        let played = score("red_score").is_some() && score("blue_score").is_some();
+        let score = |key: &str| score(key).unwrap_or(0);
+        Some(Match {
+            match_key: json["match_key"].as_str()?.to_string(),
+            event_key: json["event_key"].as_str()?.to_string(),
+            scheduled_time: json["scheduled_time"].as_str().map(|t| t.to_string()),
+            match_start_time: json["match_start_time"]
+                .as_str()
+                .filter(|_| played)
+                .map(|t| t.to_string()),
+            match_name: json["match_name"].as_str()?.to_string(),
+            tournament_level: json["tournament_level"].as_u64()? as u32,
+            red_teams,
+            blue_teams,
+            red_score: score("red_score"),
+            blue_score: score("blue_score"),
+            red_penalty: score("red_penalty"),
+            blue_penalty: score("blue_penalty"),
+            red_auto_score: score("red_auto_score"),
+            blue_auto_score: score("blue_auto_score"),
+            red_tele_score: score("red_tele_score"),
+            blue_tele_score: score("blue_tele_score"),
+            red_end_score: score("red_end_score"),
+            blue_end_score: score("blue_end_score"),
+        })
+    }
+}
+
+/// A round of an event, with the number of the series for semifinals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Round {
+    Qualification,
+    Semifinal(u32),
+    Final,
+}
+
+impl Round {
+    /// The key TOA gives match `number` of this round, and its tournament level.
+    ///
+    /// Qualification match 3 is `{event}-Q003-1` at level 1, the second match of the first
+    /// semifinal series is `{event}-E102-1` at level 21, and finals match 2 is `{event}-F002-1`
+    /// at level 4.
+    pub(crate) fn match_key(self, event_key: &str, number: u32) -> (String, u32) {
+        let (code, level) = match self {
+            Round::Qualification => (format!("Q{:03}", number), 1),
+            Round::Semifinal(series) => (format!("E{}{:02}", series, number), 20 + series),
+            Round::Final => (format!("F{:03}", number), 4),
+        };
+        (format!("{}-{}-1", event_key, code), level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Match, Round};
+
+    #[test]
+    fn keys_follow_toa() {
+        let keys: Vec<(String, u32)> = [
+            (Round::Qualification, 12),
+            (Round::Semifinal(2), 1),
+            (Round::Semifinal(1), 10),
+            (Round::Final, 3),
+        ]
+        .iter()
+        .map(|(round, number)| round.match_key("1920-TX-TRQ", *number))
+        .collect();
+        let expected = [
+            ("1920-TX-TRQ-Q012-1", 1),
+            ("1920-TX-TRQ-E201-1", 22),
+            ("1920-TX-TRQ-E110-1", 21),
+            ("1920-TX-TRQ-F003-1", 4),
+        ];
+        for ((key, level), (expected_key, expected_level)) in keys.iter().zip(expected.iter()) {
+            assert_eq!((&key[..], *level), (*expected_key, *expected_level));
+        }
+
+        let game = Match {
+            match_key: keys[2].0.clone(),
+            tournament_level: keys[2].1,
+            ..Default::default()
+        };
+        assert_eq!(game.round(), Some((Round::Semifinal(1), 10)));
+    }
+}
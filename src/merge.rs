@@ -0,0 +1,565 @@
+//! Comparing and merging the data of several backends.
+//!
+//! A [`MergedClient`](struct.MergedClient.html) asks every one of its sources for the same
+//! event, matches up the rankings by team number and the matches by their identity, and
+//! builds a single list from them. Every field the sources disagree on is reported as a
+//! [`Conflict`](struct.Conflict.html), and every record a source gives more than once as a
+//! [`Duplicate`](struct.Duplicate.html).
+//!
+//! ```no_run
+//! use rustoa::backend::{FtcEvents, FtcScout, TheOrangeAlliance};
+//! use rustoa::merge::MergedClient;
+//!
+//! let client = rustoa::Client::new("api_key");
+//! let merged = MergedClient::new(&client)
+//!     .source("ftc-events", FtcEvents::new("username", "token"))
+//!     .source("toa", TheOrangeAlliance)
+//!     .prefer("opr", &["toa"]);
+//! let rankings = merged
+//!     .event("1920-USTXTRQ")
+//!     .key("toa", "1920-TX-TRQ")
+//!     .rankings();
+//! for conflict in rankings.conflicts.iter() {
+//!     println!("{}", conflict);
+//! }
+//! ```
+
+use crate::matches::Round;
+use crate::{Backend, BackendError, Client, Match, Ranking};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A field of a record that can be compared between sources and copied from one to another.
+struct Field<T> {
+    name: &'static str,
+    show: fn(&T) -> String,
+    copy: fn(&mut T, &T),
+}
+
+macro_rules! fields {
+    ($t:ty; $($name:ident => $fmt:literal),* $(,)?) => {
+        vec![$(Field::<$t> {
+            name: stringify!($name),
+            show: |r| format!($fmt, r.$name),
+            copy: |to, from| to.$name = from.$name.clone(),
+        }),*]
+    };
+}
+
+fn ranking_fields() -> Vec<Field<Ranking>> {
+    fields![Ranking;
+        rank => "{}",
+        wins => "{}",
+        losses => "{}",
+        ties => "{}",
+        played => "{}",
+        disqualified => "{}",
+        ranking_points => "{:.2}",
+        qualifying_points => "{:.2}",
+        tiebreaker_points => "{:.2}",
+        opr => "{:.2?}",
+        np_opr => "{:.2?}",
+        highest_qual_score => "{:.2?}",
+    ]
+}
+
+fn match_fields() -> Vec<Field<Match>> {
+    fields![Match;
+        red_teams => "{:?}",
+        blue_teams => "{:?}",
+        red_score => "{}",
+        blue_score => "{}",
+        red_penalty => "{}",
+        blue_penalty => "{}",
+        red_auto_score => "{}",
+        blue_auto_score => "{}",
+        red_tele_score => "{}",
+        blue_tele_score => "{}",
+        red_end_score => "{}",
+        blue_end_score => "{}",
+    ]
+}
+
+/// A field that the sources disagree on.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conflict {
+    /// The record the field belongs to, such as `team 16405` or `Q012`.
+    pub record: String,
+    /// The name of the field, such as `red_score`.
+    pub field: String,
+    /// The value each source gave, in priority order.
+    pub values: Vec<(String, String)>,
+    /// The source whose value was used.
+    pub chosen: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(source, value)| format!("{} says {}", source, value))
+            .collect();
+        write!(
+            f,
+            "{} {}: {} (using {})",
+            self.record,
+            self.field,
+            values.join(", "),
+            self.chosen
+        )
+    }
+}
+
+/// A record that some sources did not have.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Missing {
+    pub record: String,
+    /// The sources that did not have the record.
+    pub sources: Vec<String>,
+}
+
+/// A record that one source gave more than once. Only the first of them is used.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Duplicate {
+    pub record: String,
+    /// The source that gave the record more than once.
+    pub source: String,
+    /// How many times it gave the record.
+    pub count: usize,
+}
+
+/// The merged records of every source, with what the sources disagreed on.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Merged<T> {
+    pub data: Vec<T>,
+    pub conflicts: Vec<Conflict>,
+    pub missing: Vec<Missing>,
+    pub duplicates: Vec<Duplicate>,
+    /// The sources that could not be asked, and why.
+    pub failures: Vec<(String, BackendError)>,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    name: String,
+    backend: Arc<dyn Backend>,
+}
+
+/// A client that asks several backends and merges their answers.
+#[derive(Clone, Debug)]
+pub struct MergedClient {
+    client: Client,
+    sources: Vec<Source>,
+    priorities: HashMap<String, Vec<String>>,
+}
+
+impl MergedClient {
+    /// Create a merged client with no sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client whose transport, rate limit and API key the sources use.
+    pub fn new(client: &Client) -> MergedClient {
+        MergedClient {
+            client: client.clone(),
+            sources: Vec::new(),
+            priorities: HashMap::new(),
+        }
+    }
+
+    /// Add a source. When sources disagree, the value of the source added first is used,
+    /// unless [`prefer`](#method.prefer) says otherwise for that field.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the source in conflict reports, such as `toa`.
+    /// * `backend` - The backend to ask.
+    pub fn source<B: Backend + 'static>(mut self, name: &str, backend: B) -> MergedClient {
+        self.sources.push(Source {
+            name: name.to_string(),
+            backend: Arc::new(backend),
+        });
+        self
+    }
+
+    /// Use the sources in this order for one field, such as `opr` or `red_score`. Sources
+    /// that are not listed come after them, in the order they were added.
+    pub fn prefer(mut self, field: &str, sources: &[&str]) -> MergedClient {
+        self.priorities.insert(
+            field.to_string(),
+            sources.iter().map(|s| s.to_string()).collect(),
+        );
+        self
+    }
+
+    /// An event to ask every source about.
+    ///
+    /// # Arguments
+    ///
+    /// * `event_key` - The key of the event. Use [`MergedEvent::key`](struct.MergedEvent.html#method.key)
+    ///   for the sources that know it by another key.
+    pub fn event(&self, event_key: &str) -> MergedEvent<'_> {
+        MergedEvent {
+            merged: self,
+            event_key: event_key.to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// The indices of the sources in the order to use them for a field.
+    fn order(&self, field: &str) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::new();
+        if let Some(preferred) = self.priorities.get(field) {
+            for name in preferred.iter() {
+                if let Some(i) = self.sources.iter().position(|s| &s.name == name) {
+                    order.push(i);
+                }
+            }
+        }
+        for i in 0..self.sources.len() {
+            if !order.contains(&i) {
+                order.push(i);
+            }
+        }
+        order
+    }
+
+    /// Merge the records of every source, matched up by `identity`.
+    fn merge<T: Clone>(
+        &self,
+        answers: Vec<Result<Vec<T>, BackendError>>,
+        identity: fn(&T) -> String,
+        fields: Vec<Field<T>>,
+    ) -> Merged<T> {
+        let mut failures = Vec::new();
+        let mut duplicates: Vec<Duplicate> = Vec::new();
+        // The records of each source, by identity, and every identity in the order first seen.
+        let mut records: Vec<HashMap<String, T>> = Vec::new();
+        let mut identities: Vec<String> = Vec::new();
+        for (source, answer) in self.sources.iter().zip(answers) {
+            let mut by_identity = HashMap::new();
+            match answer {
+                Ok(list) => {
+                    for record in list {
+                        let id = identity(&record);
+                        if !identities.contains(&id) {
+                            identities.push(id.clone());
+                        }
+                        if by_identity.contains_key(&id) {
+                            match duplicates
+                                .iter_mut()
+                                .find(|d| d.record == id && d.source == source.name)
+                            {
+                                Some(d) => d.count += 1,
+                                None => duplicates.push(Duplicate {
+                                    record: id,
+                                    source: source.name.clone(),
+                                    count: 2,
+                                }),
+                            }
+                            continue;
+                        }
+                        by_identity.insert(id, record);
+                    }
+                }
+                Err(e) => failures.push((source.name.clone(), e)),
+            }
+            records.push(by_identity);
+        }
+
+        let asked: Vec<usize> = (0..self.sources.len())
+            .filter(|&i| !failures.iter().any(|(n, _)| n == &self.sources[i].name))
+            .collect();
+        let mut merged = Merged {
+            data: Vec::new(),
+            conflicts: Vec::new(),
+            missing: Vec::new(),
+            duplicates,
+            failures,
+        };
+        for id in identities.iter() {
+            let without: Vec<String> = asked
+                .iter()
+                .filter(|&&i| !records[i].contains_key(id))
+                .map(|&i| self.sources[i].name.clone())
+                .collect();
+            if !without.is_empty() {
+                merged.missing.push(Missing {
+                    record: id.clone(),
+                    sources: without,
+                });
+            }
+
+            let base = match self.order("").into_iter().find_map(|i| records[i].get(id)) {
+                Some(r) => r,
+                None => continue,
+            };
+            let mut record = base.clone();
+            for field in fields.iter() {
+                let values: Vec<(usize, &T)> = self
+                    .order(field.name)
+                    .into_iter()
+                    .filter(|&i| {
+                        !self.sources[i]
+                            .backend
+                            .missing_fields()
+                            .contains(&field.name)
+                    })
+                    .filter_map(|i| records[i].get(id).map(|r| (i, r)))
+                    .collect();
+                let (chosen, from) = match values.first() {
+                    Some(v) => *v,
+                    None => continue,
+                };
+                (field.copy)(&mut record, from);
+                let shown: Vec<(String, String)> = values
+                    .iter()
+                    .map(|(i, r)| (self.sources[*i].name.clone(), (field.show)(r)))
+                    .collect();
+                if shown.iter().any(|(_, v)| v != &shown[0].1) {
+                    merged.conflicts.push(Conflict {
+                        record: id.clone(),
+                        field: field.name.to_string(),
+                        values: shown,
+                        chosen: self.sources[chosen].name.clone(),
+                    });
+                }
+            }
+            merged.data.push(record);
+        }
+        merged
+    }
+}
+
+/// An event asked about through a [`MergedClient`](struct.MergedClient.html).
+#[derive(Clone, Debug)]
+pub struct MergedEvent<'a> {
+    merged: &'a MergedClient,
+    event_key: String,
+    keys: HashMap<String, String>,
+}
+
+impl<'a> MergedEvent<'a> {
+    /// Use another key for the event with one source, such as `1920-TX-TRQ` for TOA when
+    /// the other sources use FIRST's event code.
+    pub fn key(mut self, source: &str, event_key: &str) -> MergedEvent<'a> {
+        self.keys.insert(source.to_string(), event_key.to_string());
+        self
+    }
+
+    fn key_for(&self, source: &Source) -> &str {
+        match self.keys.get(&source.name) {
+            Some(k) => k,
+            None => &self.event_key,
+        }
+    }
+
+    /// The rankings of every source, matched up by team number and sorted by the merged rank.
+    pub fn rankings(&self) -> Merged<Ranking> {
+        let client = &self.merged.client;
+        let answers = self
+            .merged
+            .sources
+            .iter()
+            .map(|s| s.backend.rankings(client, self.key_for(s)))
+            .collect();
+        let mut merged = self.merged.merge(
+            answers,
+            |r| format!("team {}", r.team_number),
+            ranking_fields(),
+        );
+        merged.data.sort_by_key(|r| r.rank);
+        merged
+    }
+
+    /// The matches of every source, matched up by their identity.
+    ///
+    /// Matches are identified by their round, series and number, such as `Q012` for the
+    /// twelfth qualification match, `SF2-1` for the first match of the second semifinal
+    /// series and `F-1` for the first finals match. A match whose key does not say these is
+    /// identified by its alliances and name.
+    pub fn matches(&self) -> Merged<Match> {
+        let client = &self.merged.client;
+        let answers = self
+            .merged
+            .sources
+            .iter()
+            .map(|s| {
+                // Every record's key starts with the source's own event key.
+                let key = self.key_for(s);
+                s.backend.matches(client, key).map(|list| {
+                    list.into_iter()
+                        .map(|mut m| {
+                            m.match_key = m.match_key.replacen(key, &self.event_key, 1);
+                            m.event_key = self.event_key.clone();
+                            m
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+        self.merged.merge(answers, match_identity, match_fields())
+    }
+}
+
+fn match_identity(m: &Match) -> String {
+    match m.round() {
+        Some((Round::Qualification, number)) => format!("Q{:03}", number),
+        Some((Round::Semifinal(series), number)) => format!("SF{}-{}", series, number),
+        Some((Round::Final, number)) => format!("F-{}", number),
+        None => {
+            let mut red = m.red_teams.clone();
+            let mut blue = m.blue_teams.clone();
+            red.sort_unstable();
+            blue.sort_unstable();
+            format!("{:?} vs {:?} ({})", red, blue, m.match_name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Duplicate, MergedClient};
+    use crate::backend::{FtcEvents, TheOrangeAlliance};
+    use crate::test_server::TestServer;
+    use crate::{Backend, BackendError, Client, EventInfo, Match, Ranking, TeamInfo};
+
+    /// TOA's data, with one score and one rank entered wrong.
+    #[derive(Debug)]
+    struct Scoreboard;
+
+    impl Backend for Scoreboard {
+        fn team(&self, client: &Client, team_number: u32) -> Result<TeamInfo, BackendError> {
+            TheOrangeAlliance.team(client, team_number)
+        }
+
+        fn event(&self, client: &Client, event_key: &str) -> Result<EventInfo, BackendError> {
+            TheOrangeAlliance.event(client, event_key)
+        }
+
+        fn rankings(&self, client: &Client, event_key: &str) -> Result<Vec<Ranking>, BackendError> {
+            let mut rankings = TheOrangeAlliance.rankings(client, event_key)?;
+            rankings[1].rank = 3;
+            Ok(rankings)
+        }
+
+        fn matches(&self, client: &Client, event_key: &str) -> Result<Vec<Match>, BackendError> {
+            let mut matches = TheOrangeAlliance.matches(client, event_key)?;
+            matches[0].red_score = 110;
+            Ok(matches)
+        }
+    }
+
+    #[test]
+    fn merges_sources_and_reports_conflicts() {
+        let toa = TestServer::toa();
+        let ftc = TestServer::ftc_events();
+        let client = Client::new("").with_base_url(toa.url());
+        let merged = MergedClient::new(&client)
+            .source("toa", TheOrangeAlliance)
+            .source(
+                "ftc-events",
+                FtcEvents::new("user", "token").with_base_url(ftc.url()),
+            )
+            .source("scoreboard", Scoreboard)
+            .prefer("red_score", &["scoreboard"]);
+        let event = merged
+            .event("1920-TX-TRQ")
+            .key("ftc-events", "1920-USTXTRQ");
+
+        let rankings = event.rankings();
+        assert!(rankings.failures.is_empty());
+        assert_eq!(rankings.conflicts.len(), 1);
+        let conflict = &rankings.conflicts[0];
+        assert_eq!(
+            conflict.record,
+            format!("team {}", rankings.data[1].team_number)
+        );
+        assert_eq!((&conflict.field[..], &conflict.chosen[..]), ("rank", "toa"));
+        assert_eq!(
+            conflict.values[2],
+            ("scoreboard".to_string(), "3".to_string())
+        );
+        assert_eq!(rankings.data[1].rank, 2);
+        // The stand-in FTC Events API only lists two teams.
+        assert!(rankings
+            .missing
+            .iter()
+            .all(|m| m.sources == vec!["ftc-events"]));
+
+        let matches = event.matches();
+        // TOA has no second semifinal.
+        assert_eq!(matches.data.len(), 15);
+        assert!(matches.duplicates.is_empty());
+        assert_eq!(matches.data[0].red_score, 110);
+        assert_eq!(matches.conflicts.len(), 1);
+        assert_eq!(
+            matches.conflicts[0].to_string(),
+            "Q001 red_score: scoreboard says 110, toa says 107, ftc-events says 107 (using scoreboard)"
+        );
+        // The playoffs are matched up by series and number, although the sources name them
+        // differently. The stand-in FTC Events API only has one qualification match.
+        let missing: Vec<(&str, &[String])> = matches
+            .missing
+            .iter()
+            .filter(|m| !m.record.starts_with('Q'))
+            .map(|m| (&m.record[..], &m.sources[..]))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![("SF2-1", &["toa".to_string(), "scoreboard".to_string()][..])]
+        );
+    }
+
+    /// TOA's data, with the first match listed twice.
+    #[derive(Debug)]
+    struct Repeats;
+
+    impl Backend for Repeats {
+        fn team(&self, client: &Client, team_number: u32) -> Result<TeamInfo, BackendError> {
+            TheOrangeAlliance.team(client, team_number)
+        }
+
+        fn event(&self, client: &Client, event_key: &str) -> Result<EventInfo, BackendError> {
+            TheOrangeAlliance.event(client, event_key)
+        }
+
+        fn rankings(&self, client: &Client, event_key: &str) -> Result<Vec<Ranking>, BackendError> {
+            TheOrangeAlliance.rankings(client, event_key)
+        }
+
+        fn matches(&self, client: &Client, event_key: &str) -> Result<Vec<Match>, BackendError> {
+            let mut matches = TheOrangeAlliance.matches(client, event_key)?;
+            let mut repeat = matches[0].clone();
+            repeat.red_score = 0;
+            matches.push(repeat);
+            Ok(matches)
+        }
+    }
+
+    #[test]
+    fn reports_records_a_source_gives_twice() {
+        let toa = TestServer::toa();
+        let client = Client::new("").with_base_url(toa.url());
+        let merged = MergedClient::new(&client)
+            .source("toa", TheOrangeAlliance)
+            .source("repeats", Repeats);
+        let matches = merged.event("1920-TX-TRQ").matches();
+        assert_eq!(
+            matches.duplicates,
+            vec![Duplicate {
+                record: "Q001".to_string(),
+                source: "repeats".to_string(),
+                count: 2
+            }]
+        );
+        assert!(matches.conflicts.is_empty());
+        assert_eq!(matches.data.len(), 14);
+    }
+}
@@ -0,0 +1,62 @@
+//! Request counters shared by every clone of a client.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of what a [`Client`](struct.Client.html) and its clones have done.
+///
+/// You can get one with [`Client::metrics`](struct.Client.html#method.metrics).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    /// Requests sent to the API.
+    pub requests: u64,
+    /// Responses served from the in-memory or disk cache without a request.
+    pub cache_hits: u64,
+    /// Requests that failed, or that the API answered with an error status.
+    pub errors: u64,
+    /// Requests the API answered with `429 Too Many Requests`.
+    pub rate_limited: u64,
+    /// Requests that waited for the client's own rate limit before being sent.
+    pub throttled: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    requests: AtomicU64,
+    cache_hits: AtomicU64,
+    errors: AtomicU64,
+    rate_limited: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn throttled(&self) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Metrics {
+        Metrics {
+            requests: self.requests.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+        }
+    }
+}
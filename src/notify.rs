@@ -0,0 +1,586 @@
+//! Posting event changes to chat webhooks.
+//!
+//! A [`Notifier`](struct.Notifier.html) turns the [`Change`](../watch/enum.Change.html)s of an
+//! event watcher into short messages using [`Templates`](struct.Templates.html), and posts them
+//! to Discord, Slack or any other webhook that accepts JSON.
+//!
+//! ```no_run
+//! use rustoa::notify::{Notifier, Templates, Webhook};
+//! use std::time::Duration;
+//!
+//! let client = rustoa::Client::new("api_key");
+//! let templates = Templates::default()
+//!     .match_scored("Match {short_name} final: Red {red_score} – Blue {blue_score}, we're now rank {rank}");
+//! let mut notifier = Notifier::new()
+//!     .team(16405)
+//!     .templates(templates)
+//!     .webhook(Webhook::discord("https://discord.com/api/webhooks/..."));
+//! notifier.watch(client.event("1920-TX-TRQ").watch(Duration::from_secs(30)));
+//! ```
+
+use crate::watch::{Change, Watcher};
+use crate::Match;
+use serde_json::json;
+use std::error::Error;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// How many times a post the webhook rate limited is tried again.
+const RATE_LIMIT_RETRIES: u32 = 2;
+/// The longest a rate limited post waits before it is tried again.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The shape of the JSON body a webhook expects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WebhookFormat {
+    /// `{"content": message}`
+    Discord,
+    /// `{"text": message}`
+    Slack,
+    /// `{"text": message, "kind": kind}`, where `kind` is the kind of change, such as
+    /// `match_scored`.
+    Json,
+}
+
+/// A URL messages are posted to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Webhook {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+impl Webhook {
+    /// A Discord webhook.
+    pub fn discord(url: &str) -> Webhook {
+        Webhook {
+            url: url.to_string(),
+            format: WebhookFormat::Discord,
+        }
+    }
+
+    /// A Slack incoming webhook.
+    pub fn slack(url: &str) -> Webhook {
+        Webhook {
+            url: url.to_string(),
+            format: WebhookFormat::Slack,
+        }
+    }
+
+    /// Any other URL that accepts a JSON POST.
+    pub fn json(url: &str) -> Webhook {
+        Webhook {
+            url: url.to_string(),
+            format: WebhookFormat::Json,
+        }
+    }
+
+    fn payload(&self, message: &str, kind: &str) -> serde_json::Value {
+        match self.format {
+            WebhookFormat::Discord => json!({ "content": message }),
+            WebhookFormat::Slack => json!({ "text": message }),
+            WebhookFormat::Json => json!({ "text": message, "kind": kind }),
+        }
+    }
+}
+
+/// The message templates for each kind of change.
+///
+/// Placeholders in braces are replaced with the details of the change:
+///
+/// | Change | Placeholders |
+/// |---|---|
+/// | Match scored | `{match_key}`, `{match_name}`, `{short_name}` (such as `Q23`), `{red_teams}`, `{blue_teams}`, `{red_score}`, `{blue_score}`, `{winner}` (`Red`, `Blue` or `Tie`) |
+/// | Score corrected | the same, plus `{old_red_score}` and `{old_blue_score}` |
+/// | Rank changed | `{team}`, `{from}`, `{to}` |
+/// | Alliance selected | `{alliance}`, `{teams}` |
+/// | Award posted | `{award}`, `{team}`, `{receiver}` |
+///
+/// Every template can also use `{rank}`, the latest known rank of the notifier's team.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Templates {
+    match_scored: String,
+    score_corrected: String,
+    rank_changed: String,
+    alliance_selected: String,
+    award_posted: String,
+}
+
+impl Default for Templates {
+    fn default() -> Templates {
+        Templates {
+            match_scored: "Match {short_name} final: Red {red_score} – Blue {blue_score}"
+                .to_string(),
+            score_corrected: "Match {short_name} corrected: Red {red_score} – Blue {blue_score} \
+                              (was {old_red_score} – {old_blue_score})"
+                .to_string(),
+            rank_changed: "Team {team} is now rank {to}".to_string(),
+            alliance_selected: "{alliance}: {teams}".to_string(),
+            award_posted: "{award}: {team}{receiver}".to_string(),
+        }
+    }
+}
+
+impl Templates {
+    pub fn match_scored(mut self, template: &str) -> Templates {
+        self.match_scored = template.to_string();
+        self
+    }
+
+    pub fn score_corrected(mut self, template: &str) -> Templates {
+        self.score_corrected = template.to_string();
+        self
+    }
+
+    pub fn rank_changed(mut self, template: &str) -> Templates {
+        self.rank_changed = template.to_string();
+        self
+    }
+
+    pub fn alliance_selected(mut self, template: &str) -> Templates {
+        self.alliance_selected = template.to_string();
+        self
+    }
+
+    pub fn award_posted(mut self, template: &str) -> Templates {
+        self.award_posted = template.to_string();
+        self
+    }
+}
+
+/// Formats changes and posts them to webhooks.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    webhooks: Vec<Webhook>,
+    templates: Templates,
+    team: Option<u32>,
+    rank: Option<u32>,
+    http: reqwest::blocking::Client,
+}
+
+impl Notifier {
+    /// Create a notifier with the default templates and no webhooks.
+    pub fn new() -> Notifier {
+        Notifier {
+            webhooks: Vec::new(),
+            templates: Templates::default(),
+            team: None,
+            rank: None,
+            http: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Add a webhook to post every message to.
+    pub fn webhook(mut self, webhook: Webhook) -> Notifier {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    pub fn templates(mut self, templates: Templates) -> Notifier {
+        self.templates = templates;
+        self
+    }
+
+    /// Only report the changes that involve this team: its matches, its rank, the alliance
+    /// it is on and its awards.
+    pub fn team(mut self, team_number: u32) -> Notifier {
+        self.team = Some(team_number);
+        self
+    }
+
+    /// Use a custom HTTP client to post messages, for example one with a proxy or timeout.
+    pub fn with_http_client(mut self, http: reqwest::blocking::Client) -> Notifier {
+        self.http = http;
+        self
+    }
+
+    /// Format a batch of changes, such as the result of one
+    /// [`Watcher::poll`](../watch/struct.Watcher.html#method.poll).
+    ///
+    /// The rank of the notifier's team is updated from the whole batch first, so a match
+    /// message can mention the rank the team has after that match.
+    pub fn messages(&mut self, changes: &[Change]) -> Vec<(&'static str, String)> {
+        for change in changes.iter() {
+            if let Change::RankChanged {
+                team_number, to, ..
+            } = change
+            {
+                if Some(*team_number) == self.team {
+                    self.rank = Some(*to);
+                }
+            }
+        }
+        changes
+            .iter()
+            .filter(|c| self.involves_team(c))
+            .map(|c| (kind(c), self.render(c)))
+            .collect()
+    }
+
+    /// Format a batch of changes and post every message to every webhook.
+    ///
+    /// A post that a webhook rate limits is tried again after the time it asks for. Any
+    /// other failure skips that post, and the remaining messages and webhooks are still
+    /// posted to.
+    ///
+    /// It returns the number of messages, or the posts that failed.
+    pub fn send(&mut self, changes: &[Change]) -> Result<usize, SendError> {
+        let messages = self.messages(changes);
+        let mut failures = Vec::new();
+        for (kind, message) in messages.iter() {
+            for webhook in self.webhooks.iter() {
+                if let Err(e) = self.post(webhook, &webhook.payload(message, kind)) {
+                    failures.push(Failure {
+                        url: webhook.url.clone(),
+                        message: message.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(messages.len())
+        } else {
+            Err(SendError {
+                messages: messages.len(),
+                failures,
+            })
+        }
+    }
+
+    /// Poll a watcher forever and post its changes.
+    ///
+    /// Failed polls are retried by the watcher, and posts that fail are skipped as
+    /// [`send`](#method.send) describes, so this never returns. Call `send` in your own loop
+    /// to see which posts failed.
+    pub fn watch(&mut self, mut watcher: Watcher) -> ! {
+        loop {
+            if let Ok(changes) = watcher.poll() {
+                let _ = self.send(&changes);
+            }
+            thread::sleep(watcher.interval());
+        }
+    }
+
+    fn post(&self, webhook: &Webhook, payload: &serde_json::Value) -> Result<(), Box<dyn Error>> {
+        let mut retries = 0;
+        loop {
+            let resp = self.http.post(&webhook.url).json(payload).send()?;
+            if resp.status().as_u16() == 429 && retries < RATE_LIMIT_RETRIES {
+                retries += 1;
+                thread::sleep(retry_after(&resp));
+                continue;
+            }
+            resp.error_for_status()?;
+            return Ok(());
+        }
+    }
+
+    fn involves_team(&self, change: &Change) -> bool {
+        let team = match self.team {
+            Some(t) => t,
+            None => return true,
+        };
+        match change {
+            Change::MatchScored(m) => m.has_team(team),
+            Change::ScoreCorrected { after, .. } => after.has_team(team),
+            Change::RankChanged { team_number, .. } => *team_number == team,
+            Change::AllianceSelected { teams, .. } => teams.contains(&team),
+            Change::AwardPosted(award) => award.team_number == Some(team),
+        }
+    }
+
+    fn render(&self, change: &Change) -> String {
+        let rank = self.rank.map(|r| r.to_string()).unwrap_or_default();
+        let mut values: Vec<(&str, String)> = vec![("rank", rank)];
+        let template = match change {
+            Change::MatchScored(m) => {
+                values.extend(match_values(m));
+                &self.templates.match_scored
+            }
+            Change::ScoreCorrected { before, after } => {
+                values.extend(match_values(after));
+                values.push(("old_red_score", before.red_score.to_string()));
+                values.push(("old_blue_score", before.blue_score.to_string()));
+                &self.templates.score_corrected
+            }
+            Change::RankChanged {
+                team_number,
+                from,
+                to,
+            } => {
+                values.push(("team", team_number.to_string()));
+                values.push(("from", from.map(|r| r.to_string()).unwrap_or_default()));
+                values.push(("to", to.to_string()));
+                &self.templates.rank_changed
+            }
+            Change::AllianceSelected { alliance, teams } => {
+                values.push(("alliance", alliance.clone()));
+                values.push(("teams", join(teams, ", ")));
+                &self.templates.alliance_selected
+            }
+            Change::AwardPosted(award) => {
+                values.push(("award", award.award_name.clone()));
+                let team = award.team_number.map(|t| t.to_string());
+                values.push(("team", team.unwrap_or_default()));
+                values.push(("receiver", award.receiver_name.clone().unwrap_or_default()));
+                &self.templates.award_posted
+            }
+        };
+        let mut message = template.clone();
+        for (name, value) in values.iter() {
+            message = message.replace(&format!("{{{}}}", name), value);
+        }
+        message
+    }
+}
+
+/// A message that could not be posted to a webhook.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub url: String,
+    pub message: String,
+    pub error: String,
+}
+
+/// The posts of a [`Notifier::send`](struct.Notifier.html#method.send) that failed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SendError {
+    /// The number of messages in the batch.
+    pub messages: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} posts failed", self.failures.len())?;
+        if let Some(first) = self.failures.first() {
+            write!(f, ", the first to {}: {}", first.url, first.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SendError {}
+
+/// How long a rate limited response asks to wait, from its `Retry-After` header in seconds.
+fn retry_after(resp: &reqwest::blocking::Response) -> Duration {
+    let seconds = resp
+        .headers()
+        .get("Retry-After")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .unwrap_or(1.0);
+    Duration::from_secs_f64(seconds).min(MAX_RETRY_AFTER)
+}
+
+impl Default for Notifier {
+    fn default() -> Notifier {
+        Notifier::new()
+    }
+}
+
+fn kind(change: &Change) -> &'static str {
+    match change {
+        Change::MatchScored(_) => "match_scored",
+        Change::ScoreCorrected { .. } => "score_corrected",
+        Change::RankChanged { .. } => "rank_changed",
+        Change::AllianceSelected { .. } => "alliance_selected",
+        Change::AwardPosted(_) => "award_posted",
+    }
+}
+
+fn join(teams: &[u32], separator: &str) -> String {
+    teams
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn match_values(m: &Match) -> Vec<(&'static str, String)> {
+    let winner = if m.red_score > m.blue_score {
+        "Red"
+    } else if m.red_score < m.blue_score {
+        "Blue"
+    } else {
+        "Tie"
+    };
+    vec![
+        ("match_key", m.match_key.clone()),
+        ("match_name", m.match_name.clone()),
+        ("short_name", short_name(&m.match_key)),
+        ("red_teams", join(&m.red_teams, " ")),
+        ("blue_teams", join(&m.blue_teams, " ")),
+        ("red_score", m.red_score.to_string()),
+        ("blue_score", m.blue_score.to_string()),
+        ("winner", winner.to_string()),
+    ]
+}
+
+/// The short name of a match, such as `Q23` for `1920-TX-TRQ-Q023-1`.
+fn short_name(match_key: &str) -> String {
+    let part = match match_key.split('-').nth(3) {
+        Some(p) => p,
+        None => return match_key.to_string(),
+    };
+    let (prefix, number) = part.split_at(part.find(|c: char| c.is_ascii_digit()).unwrap_or(0));
+    match number.parse::<u32>() {
+        Ok(n) => format!("{}{}", prefix, n),
+        Err(_) => part.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{short_name, Notifier, SendError, Templates, Webhook};
+    use crate::watch::Change;
+    use crate::Match;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn changes() -> Vec<Change> {
+        let m = Match {
+            match_key: "1920-TX-TRQ-Q023-1".to_string(),
+            red_teams: vec![16405, 8565],
+            blue_teams: vec![11260, 12456],
+            red_score: 145,
+            blue_score: 98,
+            ..Default::default()
+        };
+        vec![
+            Change::MatchScored(Box::new(m)),
+            Change::RankChanged {
+                team_number: 11260,
+                from: Some(3),
+                to: 5,
+            },
+            Change::RankChanged {
+                team_number: 16405,
+                from: Some(6),
+                to: 4,
+            },
+        ]
+    }
+
+    #[test]
+    fn formats_messages_for_a_team() {
+        assert_eq!(short_name("1920-TX-TRQ-F002-1"), "F2");
+        let templates = Templates::default().match_scored(
+            "Match {short_name} final: Red {red_score} – Blue {blue_score}, we're now rank {rank}",
+        );
+        let mut notifier = Notifier::new().team(16405).templates(templates);
+        let messages: Vec<String> = notifier
+            .messages(&changes())
+            .into_iter()
+            .map(|(_, m)| m)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Match Q23 final: Red 145 – Blue 98, we're now rank 4",
+                "Team 16405 is now rank 4"
+            ]
+        );
+        assert_eq!(Notifier::new().messages(&changes()).len(), 3);
+    }
+
+    #[test]
+    fn posts_to_webhooks() {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}", server.server_addr());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let (s, b) = (server.clone(), bodies.clone());
+        let handle = thread::spawn(move || {
+            for mut request in s.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                b.lock().unwrap().push((request.url().to_string(), body));
+                let _ = request.respond(tiny_http::Response::empty(204));
+            }
+        });
+
+        let http = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        let mut notifier = Notifier::new()
+            .team(16405)
+            .with_http_client(http)
+            .webhook(Webhook::discord(&format!("{}/discord", url)))
+            .webhook(Webhook::slack(&format!("{}/slack", url)))
+            .webhook(Webhook::json(&format!("{}/json", url)));
+        assert_eq!(notifier.send(&changes()).unwrap(), 2);
+
+        server.unblock();
+        handle.join().unwrap();
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 6);
+        assert_eq!(
+            bodies[0],
+            (
+                "/discord".to_string(),
+                r#"{"content":"Match Q23 final: Red 145 – Blue 98"}"#.to_string()
+            )
+        );
+        assert_eq!(
+            bodies[1].1,
+            r#"{"text":"Match Q23 final: Red 145 – Blue 98"}"#
+        );
+        assert_eq!(
+            bodies[5].1,
+            r#"{"kind":"rank_changed","text":"Team 16405 is now rank 4"}"#
+        );
+    }
+
+    #[test]
+    fn keeps_posting_after_a_failure() {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}", server.server_addr());
+        let posted = Arc::new(Mutex::new(Vec::new()));
+        let (s, p) = (server.clone(), posted.clone());
+        let handle = thread::spawn(move || {
+            let mut limited = false;
+            for request in s.incoming_requests() {
+                let path = request.url().to_string();
+                let response = match &path[..] {
+                    // Discord rate limits its first post.
+                    "/discord" if !limited => {
+                        limited = true;
+                        let header = "Retry-After: 0".parse::<tiny_http::Header>().unwrap();
+                        tiny_http::Response::empty(429).with_header(header)
+                    }
+                    "/broken" => tiny_http::Response::empty(500),
+                    _ => {
+                        p.lock().unwrap().push(path);
+                        tiny_http::Response::empty(204)
+                    }
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        let http = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        let mut notifier = Notifier::new()
+            .team(16405)
+            .with_http_client(http)
+            .webhook(Webhook::discord(&format!("{}/discord", url)))
+            .webhook(Webhook::json(&format!("{}/broken", url)))
+            .webhook(Webhook::slack(&format!("{}/slack", url)));
+        let err: SendError = notifier.send(&changes()).unwrap_err();
+
+        server.unblock();
+        handle.join().unwrap();
+        assert_eq!(err.messages, 2);
+        assert_eq!(err.failures.len(), 2);
+        assert!(err.failures.iter().all(|f| f.url.ends_with("/broken")));
+        assert_eq!(
+            *posted.lock().unwrap(),
+            vec!["/discord", "/slack", "/discord", "/slack"]
+        );
+    }
+}
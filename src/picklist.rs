@@ -0,0 +1,400 @@
+//! Pick lists and alliance selection.
+//!
+//! A pick list orders the teams of an event by a weighted sum of their component OPRs. It
+//! can then be used to simulate the alliance selection, including teams that decline an
+//! invitation, to see which teams are likely to still be available when it is your turn.
+
+use crate::simulation::Rng;
+use crate::{Ranking, TeamStats};
+use std::collections::{HashMap, HashSet};
+
+/// How much each scoring period counts towards a team's pick list score.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weights {
+    pub auto: f64,
+    pub teleop: f64,
+    pub endgame: f64,
+    /// The weight of the penalty points awarded to the team's alliance. These are earned by
+    /// fouls of the opposing alliance, so they count for nothing by default.
+    pub penalty: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            auto: 1.0,
+            teleop: 1.0,
+            endgame: 1.0,
+            penalty: 0.0,
+        }
+    }
+}
+
+/// A team on a pick list.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pick {
+    pub team_number: u32,
+    /// The team's qualification rank, if it is in the rankings.
+    pub rank: Option<u32>,
+    /// The weighted sum of the team's component OPRs.
+    pub score: f64,
+    /// Whether the team is on your do-not-pick list. Other captains may still pick it.
+    pub do_not_pick: bool,
+}
+
+/// The order captains pick in after the first round.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SelectionOrder {
+    /// Every round goes from the first alliance to the last, as in FTC.
+    Standard,
+    /// Every other round goes from the last alliance to the first.
+    Serpentine,
+}
+
+/// The rules and assumptions of a simulated alliance selection.
+#[derive(Clone, Debug)]
+pub struct SelectionRules {
+    alliance_count: usize,
+    alliance_size: usize,
+    order: SelectionOrder,
+    decline_probability: f64,
+    noise: f64,
+}
+
+impl SelectionRules {
+    /// Create new rules for the given number of alliances and teams per alliance, with the
+    /// standard order, no declines and every captain using the same pick list.
+    pub fn new(alliance_count: usize, alliance_size: usize) -> SelectionRules {
+        SelectionRules {
+            alliance_count,
+            alliance_size,
+            order: SelectionOrder::Standard,
+            decline_probability: 0.0,
+            noise: 0.0,
+        }
+    }
+
+    /// The order captains pick in after the first round.
+    pub fn order(mut self, order: SelectionOrder) -> SelectionRules {
+        self.order = order;
+        self
+    }
+
+    /// The probability that a team that would otherwise become a captain declines an
+    /// invitation. A team that declines can no longer be picked, but can still be a captain.
+    pub fn decline_probability(mut self, probability: f64) -> SelectionRules {
+        self.decline_probability = probability;
+        self
+    }
+
+    /// How far other captains' opinions of a team may differ from the pick list score.
+    ///
+    /// Each captain ranks the teams by their score plus random noise of this size, so that
+    /// repeated simulations cover the different choices other captains might make.
+    pub fn noise(mut self, noise: f64) -> SelectionRules {
+        self.noise = noise;
+        self
+    }
+}
+
+/// The result of a simulated alliance selection.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllianceSelection {
+    /// The teams of each alliance, captain first.
+    pub alliances: Vec<Vec<u32>>,
+    /// The teams that declined an invitation.
+    pub declined: Vec<u32>,
+    /// The teams, in pick list order, that were available when your team made its first
+    /// pick, or `None` if your team was not a captain.
+    pub available_at_our_pick: Option<Vec<u32>>,
+}
+
+/// Build a pick list from an event's rankings and the teams' component OPRs.
+///
+/// Teams on the do-not-pick list are kept, but placed after every other team.
+///
+/// # Arguments
+///
+/// * `rankings` - The qualification rankings of the event.
+/// * `stats` - The statistics of the teams, as calculated by
+///   [`stats::team_stats`](../stats/fn.team_stats.html).
+/// * `weights` - How much each scoring period counts.
+/// * `do_not_pick` - The numbers of the teams you do not want to pick.
+pub fn pick_list(
+    rankings: &[Ranking],
+    stats: &HashMap<u32, TeamStats>,
+    weights: &Weights,
+    do_not_pick: &[u32],
+) -> Vec<Pick> {
+    let mut teams: Vec<u32> = rankings.iter().map(|r| r.team_number).collect();
+    for team in stats.keys() {
+        if !teams.contains(team) {
+            teams.push(*team);
+        }
+    }
+
+    let mut picks: Vec<Pick> = teams
+        .into_iter()
+        .map(|team| {
+            let score = match stats.get(&team) {
+                Some(s) => {
+                    s.auto_opr * weights.auto
+                        + s.teleop_opr * weights.teleop
+                        + s.endgame_opr * weights.endgame
+                        + s.penalty_opr * weights.penalty
+                }
+                None => 0.0,
+            };
+            Pick {
+                team_number: team,
+                rank: rankings
+                    .iter()
+                    .find(|r| r.team_number == team)
+                    .map(|r| r.rank),
+                score,
+                do_not_pick: do_not_pick.contains(&team),
+            }
+        })
+        .collect();
+    picks.sort_by(|a, b| {
+        a.do_not_pick
+            .cmp(&b.do_not_pick)
+            .then(b.score.partial_cmp(&a.score).unwrap())
+    });
+    picks
+}
+
+/// Simulate an alliance selection once.
+///
+/// Captains are the highest-ranked teams not yet on an alliance. Every captain picks the
+/// best available team on its (noisy) copy of the pick list, except that `our_team` skips
+/// the teams on its do-not-pick list.
+///
+/// # Arguments
+///
+/// * `rankings` - The qualification rankings of the event.
+/// * `picks` - The pick list, as returned by [`pick_list`](fn.pick_list.html).
+/// * `rules` - The rules of the selection.
+/// * `our_team` - The number of your team.
+/// * `seed` - The seed of the random number generator used for declines and noise.
+pub fn simulate_selection(
+    rankings: &[Ranking],
+    picks: &[Pick],
+    rules: &SelectionRules,
+    our_team: u32,
+    seed: u64,
+) -> AllianceSelection {
+    select(rankings, picks, rules, our_team, &mut Rng::new(seed))
+}
+
+/// The probability that each team is still available when your team makes its first pick.
+///
+/// The selection is simulated `runs` times. Only the runs in which your team is a captain
+/// are counted, and the teams are returned in pick list order.
+pub fn availability(
+    rankings: &[Ranking],
+    picks: &[Pick],
+    rules: &SelectionRules,
+    our_team: u32,
+    runs: u32,
+    seed: u64,
+) -> Vec<(u32, f64)> {
+    let mut rng = Rng::new(seed);
+    let mut counts: HashMap<u32, u32> = HashMap::new();
+    let mut captained = 0;
+    for _ in 0..runs {
+        let selection = select(rankings, picks, rules, our_team, &mut rng);
+        if let Some(available) = selection.available_at_our_pick {
+            captained += 1;
+            for team in available {
+                *counts.entry(team).or_insert(0) += 1;
+            }
+        }
+    }
+    if captained == 0 {
+        return Vec::new();
+    }
+
+    picks
+        .iter()
+        .filter(|p| p.team_number != our_team && !p.do_not_pick)
+        .map(|p| {
+            let count = *counts.get(&p.team_number).unwrap_or(&0);
+            (p.team_number, count as f64 / captained as f64)
+        })
+        .collect()
+}
+
+fn select(
+    rankings: &[Ranking],
+    picks: &[Pick],
+    rules: &SelectionRules,
+    our_team: u32,
+    rng: &mut Rng,
+) -> AllianceSelection {
+    let mut ranked: Vec<&Ranking> = rankings.iter().collect();
+    ranked.sort_by_key(|r| r.rank);
+    let ranked: Vec<u32> = ranked.iter().map(|r| r.team_number).collect();
+
+    let mut taken: HashSet<u32> = HashSet::new();
+    let mut declined: Vec<u32> = Vec::new();
+    let mut alliances: Vec<Vec<u32>> = Vec::new();
+    let mut preferences: Vec<Vec<u32>> = Vec::new();
+    let mut available_at_our_pick = None;
+
+    for round in 1..rules.alliance_size {
+        let order: Vec<usize> = match rules.order {
+            SelectionOrder::Serpentine if round % 2 == 0 => {
+                (0..rules.alliance_count).rev().collect()
+            }
+            _ => (0..rules.alliance_count).collect(),
+        };
+        for slot in order {
+            if round == 1 {
+                let captain = match ranked.iter().find(|t| !taken.contains(t)) {
+                    Some(c) => *c,
+                    None => break,
+                };
+                taken.insert(captain);
+                alliances.push(vec![captain]);
+                preferences.push(preference(picks, rules.noise, rng));
+            }
+            let captain = match alliances.get(slot) {
+                Some(a) => a[0],
+                None => continue,
+            };
+            let ours = captain == our_team;
+            let skipped: Vec<u32> = picks
+                .iter()
+                .filter(|p| ours && p.do_not_pick)
+                .map(|p| p.team_number)
+                .collect();
+            let eligible = |team: &u32, taken: &HashSet<u32>, declined: &[u32]| {
+                !taken.contains(team) && !declined.contains(team) && !skipped.contains(team)
+            };
+            if ours && round == 1 {
+                let available = picks
+                    .iter()
+                    .map(|p| p.team_number)
+                    .filter(|t| eligible(t, &taken, &declined))
+                    .collect();
+                available_at_our_pick = Some(available);
+            }
+
+            for team in preferences[slot].clone() {
+                if !eligible(&team, &taken, &declined) {
+                    continue;
+                }
+                // Only a team that would otherwise be a captain has a reason to decline.
+                let captains_left = rules.alliance_count - alliances.len();
+                let would_captain = ranked
+                    .iter()
+                    .filter(|t| !taken.contains(t))
+                    .take(captains_left)
+                    .any(|t| *t == team);
+                if would_captain && rng.uniform() < rules.decline_probability {
+                    declined.push(team);
+                    continue;
+                }
+                taken.insert(team);
+                alliances[slot].push(team);
+                break;
+            }
+        }
+    }
+
+    AllianceSelection {
+        alliances,
+        declined,
+        available_at_our_pick,
+    }
+}
+
+/// A captain's copy of the pick list, with noise added to every score.
+fn preference(picks: &[Pick], noise: f64, rng: &mut Rng) -> Vec<u32> {
+    let mut scored: Vec<(u32, f64)> = picks
+        .iter()
+        .map(|p| (p.team_number, p.score + noise * rng.logistic()))
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    scored.into_iter().map(|(team, _)| team).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        availability, pick_list, simulate_selection, SelectionOrder, SelectionRules, Weights,
+    };
+    use crate::{Ranking, TeamStats};
+    use std::collections::HashMap;
+
+    fn event() -> (Vec<Ranking>, HashMap<u32, TeamStats>) {
+        let mut rankings = Vec::new();
+        let mut stats = HashMap::new();
+        // Team 1 ranks first, but team 8 scores the most.
+        for team in 1..=8 {
+            rankings.push(Ranking {
+                team_number: team,
+                rank: team,
+                ..Default::default()
+            });
+            stats.insert(
+                team,
+                TeamStats {
+                    team_number: team,
+                    matches_played: 5,
+                    opr: 0.0,
+                    np_opr: 0.0,
+                    dpr: 0.0,
+                    ccwm: 0.0,
+                    auto_opr: 0.0,
+                    teleop_opr: team as f64 * 10.0,
+                    endgame_opr: 0.0,
+                    penalty_opr: 100.0,
+                },
+            );
+        }
+        (rankings, stats)
+    }
+
+    #[test]
+    fn orders_by_weighted_score() {
+        let (rankings, stats) = event();
+        let picks = pick_list(&rankings, &stats, &Default::default(), &[8]);
+        let order: Vec<u32> = picks.iter().map(|p| p.team_number).collect();
+        assert_eq!(order, vec![7, 6, 5, 4, 3, 2, 1, 8]);
+        assert_eq!(picks[0].score, 70.0);
+        assert_eq!(picks[0].rank, Some(7));
+    }
+
+    #[test]
+    fn serpentine_selection_with_declines() {
+        let (rankings, stats) = event();
+        let picks = pick_list(&rankings, &stats, &Default::default(), &[]);
+        let rules = SelectionRules::new(2, 3).order(SelectionOrder::Serpentine);
+        let selection = simulate_selection(&rankings, &picks, &rules, 2, 1);
+        assert_eq!(selection.alliances, vec![vec![1, 8, 5], vec![2, 7, 6]]);
+        assert_eq!(selection.available_at_our_pick, Some(vec![7, 6, 5, 4, 3]));
+
+        // Team 1 declines team 2's invitation and becomes the second captain instead.
+        let (mut rankings, stats) = event();
+        rankings[1].rank = 0;
+        let weights = Weights {
+            teleop: -1.0,
+            ..Default::default()
+        };
+        let picks = pick_list(&rankings, &stats, &weights, &[]);
+        let rules = SelectionRules::new(2, 2).decline_probability(1.0);
+        let selection = simulate_selection(&rankings, &picks, &rules, 1, 1);
+        assert_eq!(selection.alliances, vec![vec![2, 3], vec![1, 4]]);
+        assert_eq!(selection.declined, vec![1]);
+
+        let available = availability(&rankings, &picks, &rules, 1, 10, 1);
+        assert_eq!(available[0], (2, 0.0));
+        assert_eq!(available[2], (4, 1.0));
+    }
+}
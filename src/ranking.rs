@@ -0,0 +1,81 @@
+//! Qualification rankings.
+
+/// A team's row in the qualification rankings of an event.
+///
+/// You can get the rankings of an event with
+/// [`Event::rankings`](struct.Event.html#method.rankings).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ranking {
+    pub team_number: u32,
+    /// The key of the event, such as `1920-TX-TRQ`.
+    pub event_key: String,
+    pub rank: u32,
+    /// How many places the team moved in its last match.
+    pub rank_change: i32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    /// The number of qualification matches the team has played.
+    pub played: u32,
+    /// The number of matches the team was disqualified from.
+    pub disqualified: u32,
+    /// The team's OPR, or `None` if it has not been computed yet.
+    pub opr: Option<f64>,
+    /// The team's OPR without penalties, or `None` if it has not been computed yet.
+    pub np_opr: Option<f64>,
+    /// The team's best qualification score, or `None` if it has not been computed yet.
+    pub highest_qual_score: Option<f64>,
+    pub ranking_points: f64,
+    pub qualifying_points: f64,
+    pub tiebreaker_points: f64,
+}
+
+impl Ranking {
+    /// A numeric field by its TOA name, such as `opr` or `tie_breaker_points`.
+    pub(crate) fn field(&self, name: &str) -> Option<f64> {
+        Some(match name {
+            "rank" => self.rank as f64,
+            "rank_change" => self.rank_change as f64,
+            "wins" => self.wins as f64,
+            "losses" => self.losses as f64,
+            "ties" => self.ties as f64,
+            "played" => self.played as f64,
+            "disqualified" => self.disqualified as f64,
+            "opr" => self.opr?,
+            "np_opr" => self.np_opr?,
+            "highest_qual_score" => self.highest_qual_score?,
+            "ranking_points" => self.ranking_points,
+            "qualifying_points" => self.qualifying_points,
+            "tie_breaker_points" => self.tiebreaker_points,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn from_json(json: &serde_json::Value) -> Option<Ranking> {
+        let count = |key: &str| json[key].as_u64().map(|n| n as u32);
+        // Team results have a team key, but no team object.
+        let team_number = match json["team"]["team_number"].as_u64() {
+            Some(n) => n as u32,
+            None => json["team_key"].as_str()?.parse().ok()?,
+        };
+        Some(Ranking {
+            team_number,
+            event_key: json["event_key"].as_str().unwrap_or("").to_string(),
+            rank: count("rank")?,
+            rank_change: json["rank_change"].as_i64().unwrap_or(0) as i32,
+            wins: count("wins")?,
+            losses: count("losses")?,
+            ties: count("ties")?,
+            played: count("played").unwrap_or(0),
+            disqualified: count("disqualified").unwrap_or(0),
+            // These are null until TOA has computed them for the team.
+            opr: json["opr"].as_f64(),
+            np_opr: json["np_opr"].as_f64(),
+            highest_qual_score: json["highest_qual_score"].as_f64(),
+            ranking_points: json["ranking_points"].as_f64()?,
+            qualifying_points: json["qualifying_points"].as_f64()?,
+            tiebreaker_points: json["tie_breaker_points"].as_f64()?,
+        })
+    }
+}
@@ -0,0 +1,290 @@
+//! Elo ratings of team strength.
+//!
+//! Win-loss records say nothing about who a team played against. An [`Elo`](struct.Elo.html)
+//! replays match results in order and moves the rating of every team on an alliance up or
+//! down depending on how surprising the result was, given the ratings of both alliances.
+
+use crate::{Event, Match};
+use std::collections::HashMap;
+
+/// The rating of a team after one of its matches.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RatingPoint {
+    /// The key of the match, such as `1920-TX-TRQ-Q001-1`.
+    pub match_key: String,
+    /// The key of the event the match was played at.
+    pub event_key: String,
+    /// The team's rating after the match.
+    pub rating: f64,
+}
+
+/// An Elo rating engine.
+///
+/// Each alliance is rated as the average of its teams' ratings. After every match, each
+/// team's rating changes by `k_factor * (result - expected)`, where `result` is `1` for a
+/// win, `0.5` for a tie and `0` for a loss.
+///
+/// ```
+/// use rustoa::{Elo, Match};
+///
+/// let win = Match {
+///     event_key: "1920-TX-TRQ".to_string(),
+///     match_start_time: Some("2020-02-01T10:00:00Z".to_string()),
+///     red_teams: vec![16405, 8565],
+///     blue_teams: vec![11260, 12456],
+///     red_score: 120,
+///     blue_score: 80,
+///     ..Default::default()
+/// };
+/// let mut elo = Elo::new().k_factor(32.0);
+/// elo.replay(&[win]);
+/// assert_eq!(elo.rating(16405), 1516.0);
+/// assert_eq!(elo.rating(11260), 1484.0);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Elo {
+    initial: f64,
+    k_factor: f64,
+    carryover: f64,
+    season_carryover: f64,
+    ratings: HashMap<u32, f64>,
+    last_event: HashMap<u32, String>,
+    history: HashMap<u32, Vec<RatingPoint>>,
+}
+
+impl Elo {
+    /// Create a new engine where every team starts at 1500, with a K-factor of 12 and
+    /// ratings carried over fully between events and seasons.
+    pub fn new() -> Elo {
+        Elo {
+            initial: 1500.0,
+            k_factor: 12.0,
+            carryover: 1.0,
+            season_carryover: 1.0,
+            ratings: HashMap::new(),
+            last_event: HashMap::new(),
+            history: HashMap::new(),
+        }
+    }
+
+    /// The rating every team starts with.
+    pub fn initial(mut self, initial: f64) -> Elo {
+        self.initial = initial;
+        self
+    }
+
+    /// The most a team's rating can change in a single match.
+    pub fn k_factor(mut self, k_factor: f64) -> Elo {
+        self.k_factor = k_factor;
+        self
+    }
+
+    /// How much of a team's rating is kept when it moves on to another event in the same
+    /// season.
+    ///
+    /// With a carryover of `0.8`, a team rated 1600 starts its next event at 1580, one fifth
+    /// of the way back to the initial rating.
+    pub fn carryover(mut self, carryover: f64) -> Elo {
+        self.carryover = carryover;
+        self
+    }
+
+    /// How much of a team's rating is kept when it plays its first event of a new season.
+    pub fn season_carryover(mut self, season_carryover: f64) -> Elo {
+        self.season_carryover = season_carryover;
+        self
+    }
+
+    /// The current rating of a team.
+    pub fn rating(&self, team_number: u32) -> f64 {
+        *self.ratings.get(&team_number).unwrap_or(&self.initial)
+    }
+
+    /// The current rating of every team that has played a match.
+    pub fn ratings(&self) -> &HashMap<u32, f64> {
+        &self.ratings
+    }
+
+    /// The rating of a team after each of its matches, oldest first.
+    pub fn history(&self, team_number: u32) -> &[RatingPoint] {
+        match self.history.get(&team_number) {
+            Some(h) => &h[..],
+            None => &[],
+        }
+    }
+
+    /// The rating an alliance would be given from the current team ratings.
+    pub fn alliance_rating(&self, teams: &[u32]) -> f64 {
+        if teams.is_empty() {
+            return self.initial;
+        }
+        teams.iter().map(|t| self.rating(*t)).sum::<f64>() / teams.len() as f64
+    }
+
+    /// The probability that an alliance rated `rating` beats one rated `opponent`.
+    pub fn expected(rating: f64, opponent: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+    }
+
+    /// Update the ratings with the result of a single match.
+    ///
+    /// Matches are expected to be recorded in the order they were played. Matches that have
+    /// not been played yet, or without teams on both alliances, are ignored.
+    pub fn record(&mut self, m: &Match) {
+        if !m.is_played() || m.red_teams.is_empty() || m.blue_teams.is_empty() {
+            return;
+        }
+        for team in m.red_teams.iter().chain(m.blue_teams.iter()) {
+            self.enter_event(*team, &m.event_key);
+        }
+
+        let red = self.alliance_rating(&m.red_teams);
+        let blue = self.alliance_rating(&m.blue_teams);
+        let result = if m.red_score > m.blue_score {
+            1.0
+        } else if m.red_score < m.blue_score {
+            0.0
+        } else {
+            0.5
+        };
+        let change = self.k_factor * (result - Elo::expected(red, blue));
+
+        for (teams, change) in [(&m.red_teams, change), (&m.blue_teams, -change)].iter() {
+            for team in teams.iter() {
+                let rating = self.rating(*team) + change;
+                self.ratings.insert(*team, rating);
+                self.history.entry(*team).or_default().push(RatingPoint {
+                    match_key: m.match_key.clone(),
+                    event_key: m.event_key.clone(),
+                    rating,
+                });
+            }
+        }
+    }
+
+    /// Update the ratings with the results of many matches, sorted by the time they were
+    /// scheduled (or started), with qualifications before semifinals before finals.
+    ///
+    /// Pass the matches of every event in a season (or a region) to rate all of its teams.
+    pub fn replay(&mut self, matches: &[Match]) {
+        let mut sorted: Vec<&Match> = matches.iter().collect();
+        sorted.sort_by(|a, b| a.chronological_key().cmp(&b.chronological_key()));
+        for m in sorted {
+            self.record(m);
+        }
+    }
+
+    /// Fetch the matches of the given events and replay them.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the matches of any event cannot be fetched.
+    pub fn replay_events(&mut self, events: &[Event]) {
+        let mut matches = Vec::new();
+        for event in events.iter() {
+            matches.extend(event.matches());
+        }
+        self.replay(&matches);
+    }
+
+    fn enter_event(&mut self, team: u32, event_key: &str) {
+        let carryover = match self.last_event.get(&team) {
+            Some(last) if last == event_key => return,
+            Some(last) if season_of(last) == season_of(event_key) => self.carryover,
+            Some(_) => self.season_carryover,
+            None => 1.0,
+        };
+        if let Some(rating) = self.ratings.get_mut(&team) {
+            *rating = self.initial + carryover * (*rating - self.initial);
+        }
+        self.last_event.insert(team, event_key.to_string());
+    }
+}
+
+impl Default for Elo {
+    fn default() -> Elo {
+        Elo::new()
+    }
+}
+
+fn season_of(event_key: &str) -> &str {
+    event_key.split('-').next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Elo;
+    use crate::Match;
+
+    fn game(event_key: &str, red: [u32; 2], blue: [u32; 2], red_score: i32) -> Match {
+        Match {
+            match_key: format!("{}-{}", event_key, red_score),
+            event_key: event_key.to_string(),
+            red_teams: red.to_vec(),
+            blue_teams: blue.to_vec(),
+            match_start_time: Some("2020-02-01T10:00:00Z".to_string()),
+            red_score,
+            blue_score: 50,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn upsets_move_ratings_more() {
+        let mut elo = Elo::new().k_factor(32.0);
+        elo.replay(&[game("1920-TX-TRQ", [1, 2], [3, 4], 100)]);
+        assert_eq!(elo.rating(1), 1516.0);
+
+        // The favourites winning again gains them less than the first win did.
+        elo.record(&game("1920-TX-TRQ", [1, 2], [3, 4], 90));
+        let gain = elo.rating(1) - 1516.0;
+        assert!(gain > 0.0 && gain < 16.0);
+
+        // A tie between equal alliances changes nothing.
+        elo.record(&game("1920-TX-TRQ", [1, 3], [2, 4], 50));
+        assert_eq!(elo.history(1).len(), 3);
+        assert!((elo.history(1)[2].rating - elo.history(1)[1].rating).abs() < 1e-9);
+    }
+
+    #[test]
+    fn carryover_between_events_and_seasons() {
+        let mut elo = Elo::new()
+            .k_factor(100.0)
+            .carryover(0.5)
+            .season_carryover(0.0);
+        elo.record(&game("1920-TX-TRQ", [1, 2], [3, 4], 100));
+        assert_eq!(elo.rating(1), 1550.0);
+
+        // Team 1 starts the next event at 1525, then ties a weaker alliance.
+        elo.record(&game("1920-TX-PLQ", [1, 5], [6, 7], 50));
+        let rating = elo.history(1)[1].rating;
+        assert!(rating < 1525.0 && rating > 1520.0);
+
+        elo.record(&game("2021-TX-TRQ", [1, 2], [3, 4], 50));
+        assert!((elo.rating(1) - 1500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn replay_skips_unplayed_matches_and_orders_playoffs() {
+        let mut qual = game("1920-TX-TRQ", [1, 2], [3, 4], 100);
+        qual.match_key = "1920-TX-TRQ-Q001-1".to_string();
+        qual.scheduled_time = Some("2020-02-01T10:00:00Z".to_string());
+        let mut unplayed = game("1920-TX-TRQ", [1, 2], [3, 4], 0);
+        unplayed.match_key = "1920-TX-TRQ-Q002-1".to_string();
+        unplayed.scheduled_time = Some("2020-02-01T10:10:00Z".to_string());
+        unplayed.match_start_time = None;
+        // Other backends give playoff matches no scheduled time, only a start time.
+        let mut semifinal = game("1920-TX-TRQ", [1, 2], [3, 4], 0);
+        semifinal.match_key = "1920-TX-TRQ-E101-1".to_string();
+        semifinal.tournament_level = 21;
+        semifinal.match_start_time = Some("2020-02-01T14:00:00Z".to_string());
+
+        let mut elo = Elo::new().k_factor(32.0);
+        elo.replay(&[semifinal, unplayed, qual]);
+        let keys: Vec<&str> = elo.history(1).iter().map(|p| &p.match_key[..]).collect();
+        assert_eq!(keys, ["1920-TX-TRQ-Q001-1", "1920-TX-TRQ-E101-1"]);
+        assert_eq!(elo.history(1)[0].rating, 1516.0);
+    }
+}
@@ -0,0 +1,281 @@
+//! Season reports.
+
+use crate::Ranking;
+use std::fmt;
+
+/// A team's result at a single event.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventResult {
+    /// The key of the event, such as `1920-TX-TRQ`.
+    pub event_key: String,
+    pub event_name: String,
+    /// The team's qualification ranking at the event.
+    pub ranking: Ranking,
+    /// The names of the awards the team won at the event.
+    pub awards: Vec<String>,
+    /// The key of the event the team went on to play in, if this event advances teams and
+    /// the team attended the next one.
+    pub advanced_to: Option<String>,
+}
+
+/// A summary of a team's season, event by event.
+///
+/// You can get a report with [`Team::season_report`](struct.Team.html#method.season_report).
+/// It implements `Display` as plain text, and can be rendered as Markdown with
+/// [`to_markdown`](#method.to_markdown).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeasonReport {
+    pub team_number: u32,
+    /// The season, such as `1920`.
+    pub season: i32,
+    /// The team's results, in the order the events were played.
+    pub events: Vec<EventResult>,
+}
+
+impl SeasonReport {
+    /// The number of qualification matches played across every event.
+    pub fn matches_played(&self) -> u32 {
+        self.events.iter().map(|e| e.ranking.played).sum()
+    }
+
+    pub fn wins(&self) -> u32 {
+        self.events.iter().map(|e| e.ranking.wins).sum()
+    }
+
+    pub fn losses(&self) -> u32 {
+        self.events.iter().map(|e| e.ranking.losses).sum()
+    }
+
+    pub fn ties(&self) -> u32 {
+        self.events.iter().map(|e| e.ranking.ties).sum()
+    }
+
+    /// The fraction of qualification matches won, lost and tied.
+    pub fn record_per_match(&self) -> (f64, f64, f64) {
+        let played = (self.wins() + self.losses() + self.ties()) as f64;
+        if played == 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        (
+            self.wins() as f64 / played,
+            self.losses() as f64 / played,
+            self.ties() as f64 / played,
+        )
+    }
+
+    /// The team's highest OPR at any event.
+    pub fn max_opr(&self) -> Option<f64> {
+        self.events
+            .iter()
+            .filter_map(|e| e.ranking.opr)
+            .fold(None, |max, opr| match max {
+                Some(m) if m >= opr => Some(m),
+                _ => Some(opr),
+            })
+    }
+
+    /// The team's average OPR over the events it has an OPR for.
+    pub fn mean_opr(&self) -> Option<f64> {
+        let oprs: Vec<f64> = self.events.iter().filter_map(|e| e.ranking.opr).collect();
+        if oprs.is_empty() {
+            return None;
+        }
+        Some(oprs.iter().sum::<f64>() / oprs.len() as f64)
+    }
+
+    /// The team's best qualification rank at any event.
+    pub fn best_rank(&self) -> Option<u32> {
+        self.events.iter().map(|e| e.ranking.rank).min()
+    }
+
+    /// Every award the team won, with the key of the event it was won at.
+    pub fn awards(&self) -> Vec<(&str, &str)> {
+        let mut awards = Vec::new();
+        for event in self.events.iter() {
+            for award in event.awards.iter() {
+                awards.push((&event.event_key[..], &award[..]));
+            }
+        }
+        awards
+    }
+
+    /// Whether the team advanced from any of its events.
+    pub fn advanced(&self) -> bool {
+        self.events.iter().any(|e| e.advanced_to.is_some())
+    }
+
+    /// Render the report as a Markdown document with a table of events.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Team {} - {} season\n\n", self.team_number, self.season);
+        out.push_str(
+            "| Event | Rank | W-L-T | OPR | NP OPR | High score | Awards | Advanced to |\n",
+        );
+        out.push_str("|---|---|---|---|---|---|---|---|\n");
+        for event in self.events.iter() {
+            let r = &event.ranking;
+            out.push_str(&format!(
+                "| {} | {} | {}-{}-{} | {} | {} | {} | {} | {} |\n",
+                escaped(&event.event_name),
+                r.rank,
+                r.wins,
+                r.losses,
+                r.ties,
+                shown(r.opr),
+                shown(r.np_opr),
+                shown(r.highest_qual_score),
+                event
+                    .awards
+                    .iter()
+                    .map(|a| escaped(a))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                event.advanced_to.as_deref().unwrap_or(""),
+            ));
+        }
+        out.push('\n');
+        for line in self.summary() {
+            out.push_str(&format!("- {}\n", line));
+        }
+        out
+    }
+
+    fn summary(&self) -> Vec<String> {
+        let (w, l, t) = self.record_per_match();
+        let mut lines = vec![
+            format!(
+                "Record: {}-{}-{} in {} matches ({:.0}% won, {:.0}% lost, {:.0}% tied)",
+                self.wins(),
+                self.losses(),
+                self.ties(),
+                self.matches_played(),
+                w * 100.0,
+                l * 100.0,
+                t * 100.0
+            ),
+            format!(
+                "OPR: {} best, {} average",
+                shown(self.max_opr()),
+                shown(self.mean_opr())
+            ),
+        ];
+        if let Some(rank) = self.best_rank() {
+            lines.push(format!("Best rank: {}", rank));
+        }
+        lines.push(format!("Awards: {}", self.awards().len()));
+        lines.push(format!(
+            "Advanced: {}",
+            if self.advanced() { "yes" } else { "no" }
+        ));
+        lines
+    }
+}
+
+/// A statistic with two decimals, or `-` if it has not been computed.
+fn shown(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{:.2}", v),
+        None => "-".to_string(),
+    }
+}
+
+/// Text for a Markdown table cell, with its pipes escaped so they do not split the cell.
+fn escaped(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+impl fmt::Display for SeasonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Team {} - {} season", self.team_number, self.season)?;
+        for event in self.events.iter() {
+            let r = &event.ranking;
+            writeln!(f)?;
+            writeln!(f, "{} ({})", event.event_name, event.event_key)?;
+            writeln!(
+                f,
+                "  Rank {}, {}-{}-{}, OPR {}, NP OPR {}, high score {}",
+                r.rank,
+                r.wins,
+                r.losses,
+                r.ties,
+                shown(r.opr),
+                shown(r.np_opr),
+                shown(r.highest_qual_score)
+            )?;
+            for award in event.awards.iter() {
+                writeln!(f, "  Award: {}", award)?;
+            }
+            if let Some(next) = &event.advanced_to {
+                writeln!(f, "  Advanced to {}", next)?;
+            }
+        }
+        writeln!(f)?;
+        for line in self.summary() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventResult, SeasonReport};
+    use crate::Ranking;
+
+    fn result(event_key: &str, rank: u32, wins: u32, losses: u32, opr: f64) -> EventResult {
+        EventResult {
+            event_key: event_key.to_string(),
+            event_name: event_key.to_string(),
+            ranking: Ranking {
+                rank,
+                wins,
+                losses,
+                played: wins + losses,
+                opr: Some(opr),
+                ..Default::default()
+            },
+            awards: Vec::new(),
+            advanced_to: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_do_not_sum_opr() {
+        let mut report = SeasonReport {
+            team_number: 16405,
+            season: 1920,
+            events: vec![
+                result("1920-TX-TRQ", 2, 4, 2, 50.0),
+                result("1920-TX-PLQ", 3, 2, 2, 60.0),
+            ],
+        };
+        report.events[1]
+            .awards
+            .push("Think Award Winner".to_string());
+
+        assert_eq!(report.max_opr(), Some(60.0));
+        assert_eq!(report.mean_opr(), Some(55.0));
+        assert_eq!(report.best_rank(), Some(2));
+        assert_eq!(report.matches_played(), 10);
+        assert_eq!(report.record_per_match(), (0.6, 0.4, 0.0));
+        assert_eq!(report.awards(), vec![("1920-TX-PLQ", "Think Award Winner")]);
+        assert!(!report.advanced());
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| 1920-TX-PLQ | 3 | 2-2-0 | 60.00 |"));
+        assert!(report.to_string().contains("Record: 6-4-0 in 10 matches"));
+
+        // Events without an OPR yet are left out of the average, and pipes in names stay
+        // inside their cell.
+        let mut pending = result("1920-TX-NTXC", 9, 0, 0, 0.0);
+        pending.ranking.opr = None;
+        pending.event_name = "North | Texas".to_string();
+        pending.awards.push("Judges' Award | Special".to_string());
+        report.events.push(pending);
+        assert_eq!(report.mean_opr(), Some(55.0));
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| North \\| Texas | 9 | 0-0-0 | - |"));
+        assert!(markdown.contains("| Judges' Award \\| Special |"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Serialization of the types that hold a client.
+//!
+//! A [`Team`](../struct.Team.html) serializes as its number and an
+//! [`Event`](../struct.Event.html) as its key, so the client and its API key are never
+//! written out. They cannot be deserialized, since there would be no client to fetch their
+//! data with; get them back with [`Client::team`](../struct.Client.html#method.team) and
+//! [`Client::event`](../struct.Client.html#method.event) instead.
+//!
+//! A [`Season`](../enum.Season.html) serializes as its key, such as `"1920"`.
+//!
+//! A [`BackendError`](../enum.BackendError.html) is read back by hand, since the name in an
+//! `Unsupported` error is a `&'static str`. That name is leaked, which is fine for the few
+//! errors a merged result holds.
+
+use crate::{BackendError, Event, Season, Team};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+impl Serialize for Team {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut team = serializer.serialize_struct("Team", 1)?;
+        team.serialize_field("team_number", &self.team_number)?;
+        team.end()
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut event = serializer.serialize_struct("Event", 1)?;
+        event.serialize_field("event_key", &self.event_key)?;
+        event.end()
+    }
+}
+
+impl Serialize for Season {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value().to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Season {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Season, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        match &key[..] {
+            "2425" => Ok(Season::IntoTheDeep),
+            "2324" => Ok(Season::CenterStage),
+            "2223" => Ok(Season::PowerPlay),
+            "2122" => Ok(Season::FreightFrenzy),
+            "2021" => Ok(Season::UltimateGoal),
+            "1920" => Ok(Season::SkyStone),
+            "1819" => Ok(Season::RoverRuckus),
+            "1718" => Ok(Season::RelicRecovery),
+            "1617" => Ok(Season::VelocityVortex),
+            _ => Err(de::Error::custom(format!("unknown season {}", key))),
+        }
+    }
+}
+
+/// The shape a [`BackendError`](../enum.BackendError.html) is serialized in.
+#[derive(serde::Deserialize)]
+#[serde(rename = "BackendError")]
+enum StoredError {
+    NotFound(String),
+    Api(u16),
+    Transport(String),
+    Json(String),
+    Query(String),
+    Unsupported(String),
+}
+
+impl<'de> Deserialize<'de> for BackendError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BackendError, D::Error> {
+        Ok(match StoredError::deserialize(deserializer)? {
+            StoredError::NotFound(key) => BackendError::NotFound(key),
+            StoredError::Api(status) => BackendError::Api(status),
+            StoredError::Transport(e) => BackendError::Transport(e),
+            StoredError::Json(e) => BackendError::Json(e),
+            StoredError::Query(e) => BackendError::Query(e),
+            StoredError::Unsupported(name) => {
+                BackendError::Unsupported(Box::leak(name.into_boxed_str()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Conflict, Merged};
+    use crate::{BackendError, Client, Match, Season};
+
+    #[test]
+    fn api_key_is_never_serialized() {
+        let client = Client::new("secret_key");
+        let team = serde_json::to_string(&client.team(16405)).unwrap();
+        let event = serde_json::to_string(&client.event("1920-TX-TRQ")).unwrap();
+        assert_eq!(team, r#"{"team_number":16405}"#);
+        assert_eq!(event, r#"{"event_key":"1920-TX-TRQ"}"#);
+
+        assert_eq!(
+            serde_json::to_string(&Season::SkyStone).unwrap(),
+            r#""1920""#
+        );
+        let season: Season = serde_json::from_str(r#""1819""#).unwrap();
+        assert_eq!(season.value(), 1819);
+        assert!(serde_json::from_str::<Season>(r#""2099""#).is_err());
+
+        let m = Match {
+            match_key: "1920-TX-TRQ-Q001-1".to_string(),
+            red_teams: vec![17010, 8565],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(serde_json::from_str::<Match>(&json).unwrap(), m);
+    }
+
+    #[test]
+    fn merged_results_round_trip() {
+        let merged = Merged {
+            data: vec![16405],
+            conflicts: vec![Conflict {
+                record: "team 16405".to_string(),
+                field: "rank".to_string(),
+                values: vec![("toa".to_string(), "2".to_string())],
+                chosen: "toa".to_string(),
+            }],
+            missing: Vec::new(),
+            duplicates: Vec::new(),
+            failures: vec![(
+                "ftc-events".to_string(),
+                BackendError::Unsupported("quick stats"),
+            )],
+        };
+        let json = serde_json::to_string(&merged).unwrap();
+        let back: Merged<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.data, back.conflicts), (merged.data, merged.conflicts));
+        assert_eq!(back.failures, merged.failures);
+    }
+}
@@ -0,0 +1,482 @@
+//! Monte Carlo simulation of qualification rankings.
+//!
+//! The qualification matches that have not been played yet are simulated many times with a
+//! [`Model`](../stats/enum.Model.html), and the teams are ranked after every run, which gives
+//! the distribution of each team's final rank.
+
+use crate::stats::{team_strength, Model};
+use crate::{Match, Season};
+use std::collections::HashMap;
+
+/// The distribution of a team's final qualification rank over every simulated run.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RankDistribution {
+    pub team_number: u32,
+    /// The number of runs that were simulated.
+    pub runs: u32,
+    /// How many runs the team finished at each rank. The first element is rank 1.
+    pub rank_counts: Vec<u32>,
+    /// The probability that the team finishes in the top four.
+    pub top_four: f64,
+    /// The estimated probability that the team ends up on an alliance for the eliminations,
+    /// either as a captain or as a pick.
+    ///
+    /// Captains are the top-ranked teams, and the remaining spots are assumed to go to the
+    /// strongest teams according to the model.
+    pub picked: f64,
+}
+
+impl RankDistribution {
+    /// The probability that the team finishes at the given rank.
+    pub fn probability_of_rank(&self, rank: usize) -> f64 {
+        match rank.checked_sub(1).and_then(|i| self.rank_counts.get(i)) {
+            Some(count) => *count as f64 / self.runs as f64,
+            None => 0.0,
+        }
+    }
+
+    /// The average final rank of the team.
+    pub fn mean_rank(&self) -> f64 {
+        let total: u64 = self
+            .rank_counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (i as u64 + 1) * *count as u64)
+            .sum();
+        total as f64 / self.runs as f64
+    }
+}
+
+/// How teams are ranked after the qualification matches.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RankingRules {
+    /// Used until the 2020-2021 season: teams are ranked by their total qualifying points
+    /// (2 for a win, 1 for a tie), then by ranking points (the losing alliance's score), then
+    /// by tiebreaker points (the losing alliance's score without penalties).
+    Totals,
+    /// Used since the 2021-2022 season: teams are ranked by their average ranking score
+    /// (2 for a win, 1 for a tie), then by their alliances' average autonomous points, then
+    /// by their average endgame points.
+    Averages,
+}
+
+impl RankingRules {
+    /// The rules used in a season.
+    pub fn for_season(season: Season) -> RankingRules {
+        RankingRules::for_season_key(season.value())
+    }
+
+    /// The rules used in a season, given as a key such as `1920`.
+    pub(crate) fn for_season_key(season: i32) -> RankingRules {
+        if season >= Season::FreightFrenzy.value() {
+            RankingRules::Averages
+        } else {
+            RankingRules::Totals
+        }
+    }
+}
+
+/// The number of teams on each alliance at an event with `teams` teams in a season given as
+/// a key such as `1920`.
+///
+/// Until the 2023-2024 season, events with more than 20 teams played with alliances of
+/// three; since then, every alliance has two teams.
+pub fn alliance_size(season: i32, teams: usize) -> u32 {
+    if season < Season::CenterStage.value() && teams > 20 {
+        3
+    } else {
+        2
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Standing {
+    played: u32,
+    qualifying_points: u32,
+    ranking_points: f64,
+    tiebreaker_points: f64,
+    auto_points: f64,
+    end_points: f64,
+}
+
+impl Standing {
+    /// The numbers the team is ranked by, best first.
+    fn sort_keys(&self, rules: RankingRules) -> [f64; 3] {
+        match rules {
+            RankingRules::Totals => [
+                self.qualifying_points as f64,
+                self.ranking_points,
+                self.tiebreaker_points,
+            ],
+            RankingRules::Averages => {
+                let played = self.played.max(1) as f64;
+                [
+                    self.qualifying_points as f64 / played,
+                    self.auto_points / played,
+                    self.end_points / played,
+                ]
+            }
+        }
+    }
+}
+
+/// The scores of one alliance in a match.
+#[derive(Clone, Copy)]
+struct Scores {
+    total: f64,
+    no_penalty: f64,
+    auto: f64,
+    end: f64,
+}
+
+/// Simulate the rest of the qualification matches `runs` times.
+///
+/// Played matches count as they are, and every match that has not been played is given a
+/// random margin around the one predicted by `model`. Teams are then ranked by `rules`; see
+/// [`RankingRules::for_season`](enum.RankingRules.html#method.for_season) for the rules of
+/// each season.
+///
+/// # Arguments
+///
+/// * `matches` - The qualification matches of the event, played or not.
+/// * `runs` - The number of times to simulate the event.
+/// * `model` - The model used to predict each match.
+/// * `alliance_count` - The number of alliances in the eliminations.
+/// * `alliance_size` - The number of teams on each alliance; see
+///   [`alliance_size`](fn.alliance_size.html) for the size used at an event.
+/// * `rules` - How teams are ranked.
+/// * `seed` - The seed of the random number generator. The same seed gives the same result.
+pub fn simulate_rankings(
+    matches: &[Match],
+    runs: u32,
+    model: &Model,
+    alliance_count: u32,
+    alliance_size: u32,
+    rules: RankingRules,
+    seed: u64,
+) -> HashMap<u32, RankDistribution> {
+    let mut teams: Vec<u32> = Vec::new();
+    for m in matches.iter() {
+        for team in m.red_teams.iter().chain(m.blue_teams.iter()) {
+            if !teams.contains(team) {
+                teams.push(*team);
+            }
+        }
+    }
+    teams.sort();
+
+    let mut played: HashMap<u32, Standing> =
+        teams.iter().map(|t| (*t, Standing::default())).collect();
+    let mut totals = Vec::new();
+    let mut remaining = Vec::new();
+    for m in matches.iter() {
+        if m.is_played() {
+            let scores = |total: i32, penalty: i32, auto: i32, end: i32| Scores {
+                total: total as f64,
+                no_penalty: (total - penalty) as f64,
+                auto: auto as f64,
+                end: end as f64,
+            };
+            add_result(
+                &mut played,
+                m,
+                scores(
+                    m.red_score,
+                    m.red_penalty,
+                    m.red_auto_score,
+                    m.red_end_score,
+                ),
+                scores(
+                    m.blue_score,
+                    m.blue_penalty,
+                    m.blue_auto_score,
+                    m.blue_end_score,
+                ),
+            );
+            totals.push((m.red_score + m.blue_score) as f64);
+        } else {
+            remaining.push(m);
+        }
+    }
+    let average_total = match totals.len() {
+        0 => 0.0,
+        n => totals.iter().sum::<f64>() / n as f64,
+    };
+
+    let captains = alliance_count as usize;
+    let spots = captains * alliance_size as usize;
+
+    let mut by_strength = teams.clone();
+    by_strength.sort_by(|a, b| team_strength(model, *b).total_cmp(&team_strength(model, *a)));
+
+    let mut rank_counts: HashMap<u32, Vec<u32>> =
+        teams.iter().map(|t| (*t, vec![0; teams.len()])).collect();
+    let mut picked: HashMap<u32, u32> = HashMap::new();
+    let mut rng = Rng::new(seed);
+
+    for _ in 0..runs {
+        let mut standings = played.clone();
+        for m in remaining.iter() {
+            let prediction = crate::stats::predict(&m.red_teams, &m.blue_teams, model);
+            let margin = prediction.expected_margin + spread_of(model) * rng.logistic();
+            let total = match model {
+                Model::Opr { stats, .. } | Model::ComponentOpr { stats, .. } => m
+                    .red_teams
+                    .iter()
+                    .chain(m.blue_teams.iter())
+                    .filter_map(|t| stats.get(t))
+                    .map(|s| s.opr)
+                    .sum(),
+                Model::Elo { .. } => average_total,
+            };
+            let red = ((total + margin) / 2.0).max(0.0).round();
+            let blue = ((total - margin) / 2.0).max(0.0).round();
+            add_result(
+                &mut standings,
+                m,
+                simulated(model, &m.red_teams, red),
+                simulated(model, &m.blue_teams, blue),
+            );
+        }
+
+        let mut ranking = teams.clone();
+        ranking.sort_by(|a, b| {
+            let (a, b) = (standings[a].sort_keys(rules), standings[b].sort_keys(rules));
+            b.iter()
+                .zip(a.iter())
+                .map(|(b, a)| b.total_cmp(a))
+                .find(|o| o.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        for (i, team) in ranking.iter().enumerate() {
+            rank_counts.get_mut(team).unwrap()[i] += 1;
+        }
+        let mut alliance: Vec<u32> = ranking.iter().take(captains).cloned().collect();
+        for team in by_strength.iter() {
+            if alliance.len() >= spots {
+                break;
+            }
+            if !alliance.contains(team) {
+                alliance.push(*team);
+            }
+        }
+        for team in alliance {
+            *picked.entry(team).or_insert(0) += 1;
+        }
+    }
+
+    let mut distributions = HashMap::new();
+    for team in teams.iter() {
+        let counts = rank_counts.remove(team).unwrap();
+        let top: u32 = counts.iter().take(4).sum();
+        distributions.insert(
+            *team,
+            RankDistribution {
+                team_number: *team,
+                runs,
+                rank_counts: counts,
+                top_four: top as f64 / runs as f64,
+                picked: *picked.get(team).unwrap_or(&0) as f64 / runs as f64,
+            },
+        );
+    }
+    distributions
+}
+
+fn add_result(standings: &mut HashMap<u32, Standing>, m: &Match, red: Scores, blue: Scores) {
+    // Both alliances get the score of the losing alliance as ranking points, and its score
+    // without penalties as tiebreaker points.
+    let ranking_points = red.total.min(blue.total);
+    let tiebreaker_points = if red.total < blue.total {
+        red.no_penalty
+    } else if blue.total < red.total {
+        blue.no_penalty
+    } else {
+        red.no_penalty.min(blue.no_penalty)
+    };
+    for (teams, own, other) in [(&m.red_teams, red, blue), (&m.blue_teams, blue, red)].iter() {
+        let qualifying_points = if own.total > other.total {
+            2
+        } else if own.total < other.total {
+            0
+        } else {
+            1
+        };
+        for team in teams.iter() {
+            let standing = standings.entry(*team).or_default();
+            standing.played += 1;
+            standing.qualifying_points += qualifying_points;
+            standing.ranking_points += ranking_points;
+            standing.tiebreaker_points += tiebreaker_points;
+            standing.auto_points += own.auto;
+            standing.end_points += own.end;
+        }
+    }
+}
+
+/// The scores of a simulated alliance. The autonomous and endgame points are those predicted
+/// by the component OPRs of its teams, or none for other models.
+fn simulated(model: &Model, teams: &[u32], total: f64) -> Scores {
+    let (auto, end) = match model {
+        Model::ComponentOpr { stats, .. } => teams
+            .iter()
+            .filter_map(|t| stats.get(t))
+            .fold((0.0, 0.0), |(a, e), s| (a + s.auto_opr, e + s.endgame_opr)),
+        _ => (0.0, 0.0),
+    };
+    Scores {
+        total,
+        no_penalty: total,
+        auto,
+        end,
+    }
+}
+
+/// The logistic scale of the margin error of a model.
+fn spread_of(model: &Model) -> f64 {
+    let spread = match model {
+        Model::Opr { spread, .. } | Model::ComponentOpr { spread, .. } => *spread,
+        Model::Elo { spread, .. } => *spread,
+    };
+    spread * 3f64.sqrt() / std::f64::consts::PI
+}
+
+/// A small xorshift random number generator, so that simulations can be repeated.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+    }
+
+    /// A uniform number in `(0, 1)`.
+    pub(crate) fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// A sample from the standard logistic distribution.
+    pub(crate) fn logistic(&mut self) -> f64 {
+        let u = self.uniform();
+        (u / (1.0 - u)).ln()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{alliance_size, simulate_rankings, RankingRules};
+    use crate::stats::Model;
+    use crate::{Elo, Match};
+
+    fn quals(red: [u32; 2], blue: [u32; 2], scores: Option<(i32, i32)>) -> Match {
+        let (red_score, blue_score) = scores.unwrap_or((0, 0));
+        Match {
+            match_key: format!("{:?}{:?}", red, blue),
+            match_start_time: scores.map(|_| "2019-12-07T15:00:00.000Z".to_string()),
+            tournament_level: 1,
+            red_teams: red.to_vec(),
+            blue_teams: blue.to_vec(),
+            red_score,
+            blue_score,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ranks_by_qualifying_then_ranking_points() {
+        let matches = vec![
+            quals([1, 2], [3, 4], Some((100, 50))),
+            quals([1, 3], [2, 4], Some((80, 60))),
+            quals([1, 4], [2, 3], Some((90, 40))),
+        ];
+        let elo = Elo::new();
+        let model = Model::Elo {
+            elo: &elo,
+            spread: 20.0,
+        };
+        let ranks = simulate_rankings(&matches, 10, &model, 1, 2, RankingRules::Totals, 7);
+        // Team 1 won every match, the others won one each.
+        assert_eq!(ranks[&1].rank_counts[0], 10);
+        assert_eq!(ranks[&1].probability_of_rank(1), 1.0);
+        assert_eq!(ranks[&4].top_four, 1.0);
+        assert_eq!(ranks[&1].picked, 1.0);
+    }
+
+    #[test]
+    fn seasons_rank_by_their_own_rules() {
+        let mut matches = vec![
+            quals([1, 2], [3, 4], Some((50, 100))),
+            quals([1, 3], [2, 4], Some((40, 100))),
+            quals([1, 4], [2, 5], Some((100, 50))),
+            quals([3, 5], [6, 7], Some((100, 50))),
+        ];
+        matches[2].red_auto_score = 60;
+        let elo = Elo::new();
+        let model = Model::Elo {
+            elo: &elo,
+            spread: 20.0,
+        };
+        let rank = |rules: RankingRules, team: u32| {
+            simulate_rankings(&matches, 1, &model, 1, 2, rules, 7)[&team].mean_rank()
+        };
+
+        assert_eq!(
+            RankingRules::for_season(crate::Season::SkyStone),
+            RankingRules::Totals
+        );
+        assert_eq!(
+            RankingRules::for_season(crate::Season::PowerPlay),
+            RankingRules::Averages
+        );
+        // Teams 1, 2 and 5 each won one match, but team 5 only played two.
+        assert_eq!(rank(RankingRules::Totals, 5), 5.0);
+        assert_eq!(rank(RankingRules::Averages, 5), 3.0);
+        // Teams 1 and 2 have the same average, and team 1 scored more autonomous points.
+        assert_eq!(rank(RankingRules::Averages, 1), 4.0);
+        assert_eq!(rank(RankingRules::Averages, 2), 5.0);
+    }
+
+    #[test]
+    fn unplayed_matches_are_simulated() {
+        let mut elo = Elo::new().k_factor(200.0);
+        elo.record(&quals([1, 2], [3, 4], Some((100, 0))));
+        let matches = vec![quals([1, 2], [3, 4], None), quals([1, 3], [2, 4], None)];
+        let model = Model::Elo {
+            elo: &elo,
+            spread: 20.0,
+        };
+        let ranks = simulate_rankings(&matches, 1000, &model, 2, 2, RankingRules::Totals, 42);
+        assert_eq!(ranks[&1].rank_counts.iter().sum::<u32>(), 1000);
+        assert!(ranks[&1].mean_rank() < ranks[&4].mean_rank());
+        assert_eq!(
+            ranks,
+            simulate_rankings(&matches, 1000, &model, 2, 2, RankingRules::Totals, 42)
+        );
+    }
+
+    #[test]
+    fn alliances_fill_with_the_given_size() {
+        assert_eq!(alliance_size(1920, 24), 3);
+        assert_eq!(alliance_size(1920, 20), 2);
+        assert_eq!(alliance_size(2324, 24), 2);
+
+        let matches = vec![
+            quals([1, 2], [3, 4], Some((100, 50))),
+            quals([5, 6], [7, 8], Some((100, 50))),
+        ];
+        let elo = Elo::new();
+        let model = Model::Elo {
+            elo: &elo,
+            spread: 20.0,
+        };
+        for size in 2..=3 {
+            let ranks = simulate_rankings(&matches, 1, &model, 2, size, RankingRules::Totals, 7);
+            let picked = ranks.values().filter(|r| r.picked == 1.0).count();
+            assert_eq!(picked, 2 * size as usize);
+        }
+    }
+}
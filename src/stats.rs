@@ -0,0 +1,785 @@
+//! Team statistics calculated locally from match results.
+//!
+//! The Orange Alliance only publishes OPR, and only updates it some time after matches are
+//! played. The functions here compute OPR, DPR, CCWM and the OPR of each scoring period
+//! directly from a list of [`Match`](../struct.Match.html) results, so they can be used in the
+//! middle of an event or on any subset of its matches.
+//!
+//! Every statistic is the least-squares solution of "the alliance's number is the sum of
+//! its teams' numbers" over every alliance in the given matches.
+//!
+//! These numbers, or the ratings of an [`Elo`](../struct.Elo.html), can then be used to
+//! [predict](fn.predict_match.html) the outcome of a match, and a model can be checked
+//! against past matches with [`backtest`](fn.backtest.html).
+
+use crate::{Elo, Match, Team};
+use std::collections::HashMap;
+
+// The numbers solved for are score, non-penalty score, opponent score, auto, teleop,
+// endgame and penalty points.
+const COLUMNS: usize = 7;
+
+/// The calculated statistics of a single team.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TeamStats {
+    pub team_number: u32,
+    /// The number of the given matches the team played in.
+    pub matches_played: u32,
+    /// Offensive Power Rating: the team's average contribution to its alliance's score.
+    pub opr: f64,
+    /// The OPR without the penalty points awarded to the team's alliance.
+    pub np_opr: f64,
+    /// Defensive Power Rating: the team's average contribution to its opponents' score.
+    pub dpr: f64,
+    /// Calculated Contribution to Winning Margin, which is `opr - dpr`.
+    pub ccwm: f64,
+    /// The OPR of the autonomous period.
+    pub auto_opr: f64,
+    /// The OPR of the driver-controlled period.
+    pub teleop_opr: f64,
+    /// The OPR of the endgame.
+    pub endgame_opr: f64,
+    /// The team's average contribution to the penalty points awarded to its alliance.
+    pub penalty_opr: f64,
+}
+
+/// Calculate the statistics of every team that played in the given matches.
+///
+/// Pass only qualification matches to get numbers comparable to The Orange Alliance's OPR;
+/// [`Event::stats`](../struct.Event.html#method.stats) does this for you.
+///
+/// When there are too few matches to tell some teams apart (for example early in an event),
+/// the numbers of those teams are split evenly between them.
+///
+/// ```
+/// use rustoa::{stats, Match};
+///
+/// let quals = |red: [u32; 2], blue: [u32; 2], red_score: i32, blue_score: i32| Match {
+///     tournament_level: 1,
+///     red_teams: red.to_vec(),
+///     blue_teams: blue.to_vec(),
+///     red_score,
+///     blue_score,
+///     red_tele_score: red_score,
+///     blue_tele_score: blue_score,
+///     ..Default::default()
+/// };
+/// let matches = vec![
+///     quals([1, 2], [3, 4], 30, 70),
+///     quals([1, 3], [2, 4], 40, 60),
+///     quals([1, 4], [2, 3], 50, 50),
+/// ];
+/// let stats = stats::team_stats(&matches);
+/// assert!((stats[&4].opr - 40.0).abs() < 1e-6);
+/// ```
+pub fn team_stats(matches: &[Match]) -> HashMap<u32, TeamStats> {
+    let mut teams: Vec<u32> = Vec::new();
+    for m in matches.iter() {
+        for team in m.red_teams.iter().chain(m.blue_teams.iter()) {
+            if !teams.contains(team) {
+                teams.push(*team);
+            }
+        }
+    }
+    teams.sort();
+    let index: HashMap<u32, usize> = teams.iter().enumerate().map(|(i, t)| (*t, i)).collect();
+    let n = teams.len();
+
+    let mut normal = vec![vec![0.0; n]; n];
+    let mut rhs = vec![[0.0; COLUMNS]; n];
+    let mut played = vec![0; n];
+
+    for m in matches.iter() {
+        let red = [
+            m.red_score,
+            m.red_score - m.red_penalty,
+            m.blue_score,
+            m.red_auto_score,
+            m.red_tele_score,
+            m.red_end_score,
+            m.red_penalty,
+        ];
+        let blue = [
+            m.blue_score,
+            m.blue_score - m.blue_penalty,
+            m.red_score,
+            m.blue_auto_score,
+            m.blue_tele_score,
+            m.blue_end_score,
+            m.blue_penalty,
+        ];
+        for (alliance, values) in [(&m.red_teams, red), (&m.blue_teams, blue)].iter() {
+            for a in alliance.iter() {
+                let i = index[a];
+                played[i] += 1;
+                for b in alliance.iter() {
+                    normal[i][index[b]] += 1.0;
+                }
+                for (c, value) in values.iter().enumerate() {
+                    rhs[i][c] += *value as f64;
+                }
+            }
+        }
+    }
+
+    let solution = solve(normal, rhs);
+
+    let mut stats = HashMap::new();
+    for (i, team) in teams.iter().enumerate() {
+        let x = &solution[i];
+        stats.insert(
+            *team,
+            TeamStats {
+                team_number: *team,
+                matches_played: played[i],
+                opr: x[0],
+                np_opr: x[1],
+                dpr: x[2],
+                ccwm: x[0] - x[2],
+                auto_opr: x[3],
+                teleop_opr: x[4],
+                endgame_opr: x[5],
+                penalty_opr: x[6],
+            },
+        );
+    }
+    stats
+}
+
+/// Solve `a * x = b` for a symmetric positive semi-definite `a` using a Cholesky
+/// decomposition.
+///
+/// If `a` is singular, the smallest possible multiple of the identity matrix is added to it,
+/// which gives (very nearly) the minimum-norm least-squares solution.
+fn solve(a: Vec<Vec<f64>>, b: Vec<[f64; COLUMNS]>) -> Vec<[f64; COLUMNS]> {
+    let n = a.len();
+    let scale = (0..n).map(|i| a[i][i]).fold(1.0, f64::max);
+    let mut ridge = 0.0;
+    loop {
+        if let Some(l) = cholesky(&a, ridge, scale * 1e-9) {
+            let mut x = b;
+            // Forward substitution with L, then back substitution with L^T.
+            for i in 0..n {
+                let mut row = x[i];
+                for (k, xk) in x.iter().enumerate().take(i) {
+                    for (r, v) in row.iter_mut().zip(xk.iter()) {
+                        *r -= l[i][k] * v;
+                    }
+                }
+                x[i] = row.map(|r| r / l[i][i]);
+            }
+            for i in (0..n).rev() {
+                let mut row = x[i];
+                for (k, xk) in x.iter().enumerate().skip(i + 1) {
+                    for (r, v) in row.iter_mut().zip(xk.iter()) {
+                        *r -= l[k][i] * v;
+                    }
+                }
+                x[i] = row.map(|r| r / l[i][i]);
+            }
+            return x;
+        }
+        ridge = if ridge == 0.0 {
+            scale * 1e-6
+        } else {
+            ridge * 10.0
+        };
+    }
+}
+
+fn cholesky(a: &[Vec<f64>], ridge: f64, tolerance: f64) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let mut sum = a[i][j];
+            if i == j {
+                sum += ridge;
+            }
+            sum -= (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+            if i == j {
+                if sum <= tolerance {
+                    return None;
+                }
+                l[i][i] = sum.sqrt();
+            } else {
+                l[i][j] = sum / l[j][j];
+            }
+        }
+    }
+    Some(l)
+}
+
+/// The numbers used to predict the outcome of a match.
+#[derive(Clone, Copy, Debug)]
+pub enum Model<'a> {
+    /// The sum of each alliance's OPRs.
+    Opr {
+        stats: &'a HashMap<u32, TeamStats>,
+        /// The standard deviation of the error of the predicted score margin, as returned by
+        /// [`margin_spread`](fn.margin_spread.html).
+        spread: f64,
+    },
+    /// The sum of each alliance's auto, teleop and endgame OPRs, leaving out penalty points,
+    /// which are much harder to predict.
+    ComponentOpr {
+        stats: &'a HashMap<u32, TeamStats>,
+        /// The standard deviation of the error of the predicted score margin.
+        spread: f64,
+    },
+    /// The average Elo rating of each alliance.
+    Elo {
+        elo: &'a Elo,
+        /// The standard deviation of the score margin, used to turn the win probability
+        /// into an expected margin.
+        spread: f64,
+    },
+}
+
+/// The predicted outcome of a match.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prediction {
+    /// The probability that the red alliance wins.
+    pub red_win_probability: f64,
+    /// The expected red score minus the expected blue score.
+    pub expected_margin: f64,
+}
+
+impl Prediction {
+    /// The probability that the blue alliance wins.
+    pub fn blue_win_probability(&self) -> f64 {
+        1.0 - self.red_win_probability
+    }
+}
+
+/// How well a model predicted past matches.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
+    /// The number of matches that were predicted.
+    pub matches: u32,
+    /// The mean squared difference between the predicted red win probability and the
+    /// result (`1` for a red win, `0.5` for a tie and `0` for a blue win). Lower is better,
+    /// and always guessing 50% scores `0.25`.
+    pub brier_score: f64,
+    /// The fraction of matches, leaving out ties, whose winner was given the higher
+    /// probability.
+    pub accuracy: f64,
+}
+
+/// The model a [`backtest`](fn.backtest.html) rebuilds before every match.
+#[derive(Clone, Debug)]
+pub enum Backtest {
+    Opr,
+    ComponentOpr,
+    /// Ratings from the given engine, which should not have recorded any matches yet.
+    Elo(Elo),
+}
+
+/// Predict the outcome of a match between two alliances.
+///
+/// Teams the model has no numbers for count as zero OPR or the initial Elo rating.
+///
+/// ```no_run
+/// use rustoa::stats::{self, Model};
+///
+/// let client = rustoa::Client::new("api_key");
+/// let event = client.event("1920-TX-TRQ");
+/// let quals: Vec<_> = event.matches().into_iter().filter(|m| m.is_qualification()).collect();
+/// let team_stats = stats::team_stats(&quals);
+/// let model = Model::Opr {
+///     stats: &team_stats,
+///     spread: stats::margin_spread(&quals, &team_stats),
+/// };
+/// let prediction = stats::predict_match(
+///     &[client.team(16405), client.team(8565)],
+///     &[client.team(11260), client.team(12456)],
+///     &model,
+/// );
+/// println!("We win {:.0}% of the time", prediction.red_win_probability * 100.0);
+/// ```
+pub fn predict_match(red: &[Team], blue: &[Team], model: &Model) -> Prediction {
+    let numbers = |teams: &[Team]| teams.iter().map(|t| t.team_number).collect::<Vec<u32>>();
+    predict(&numbers(red), &numbers(blue), model)
+}
+
+/// Predict the outcome of a match between two alliances, given by team number.
+pub fn predict(red: &[u32], blue: &[u32], model: &Model) -> Prediction {
+    let strengths =
+        |teams: &[u32]| -> Vec<f64> { teams.iter().map(|t| team_strength(model, *t)).collect() };
+    outcome(model, &strengths(red), &strengths(blue))
+}
+
+/// The OPR or Elo rating of a team according to a model.
+pub(crate) fn team_strength(model: &Model, team: u32) -> f64 {
+    match model {
+        Model::Opr { stats, .. } => stats.get(&team).map(|s| s.opr).unwrap_or(0.0),
+        Model::ComponentOpr { stats, .. } => stats
+            .get(&team)
+            .map(|s| s.auto_opr + s.teleop_opr + s.endgame_opr)
+            .unwrap_or(0.0),
+        Model::Elo { elo, .. } => elo.rating(team),
+    }
+}
+
+/// The predicted outcome of a match between alliances with the given team strengths.
+fn outcome(model: &Model, red: &[f64], blue: &[f64]) -> Prediction {
+    match model {
+        Model::Opr { spread, .. } | Model::ComponentOpr { spread, .. } => {
+            let margin = red.iter().sum::<f64>() - blue.iter().sum::<f64>();
+            Prediction {
+                red_win_probability: logistic(margin / logistic_scale(*spread)),
+                expected_margin: margin,
+            }
+        }
+        Model::Elo { elo, spread } => {
+            let average = |teams: &[f64]| match teams.len() {
+                0 => elo.alliance_rating(&[]),
+                n => teams.iter().sum::<f64>() / n as f64,
+            };
+            let p = Elo::expected(average(red), average(blue));
+            // Keep the margin finite when one alliance is rated far above the other.
+            let clamped = p.clamp(1e-6, 1.0 - 1e-6);
+            Prediction {
+                red_win_probability: p,
+                expected_margin: logistic_scale(*spread) * (clamped / (1.0 - clamped)).ln(),
+            }
+        }
+    }
+}
+
+/// The standard deviation of the difference between the margins predicted from OPR and
+/// the actual margins of the given matches.
+///
+/// This is never less than one point, so that a model fitted to very few matches is not
+/// certain of every outcome.
+pub fn margin_spread(matches: &[Match], stats: &HashMap<u32, TeamStats>) -> f64 {
+    let opr = |teams: &[u32]| -> f64 {
+        teams
+            .iter()
+            .filter_map(|t| stats.get(t))
+            .map(|s| s.opr)
+            .sum()
+    };
+    let mut sum = 0.0;
+    let mut count = 0;
+    for m in matches.iter() {
+        let predicted = opr(&m.red_teams) - opr(&m.blue_teams);
+        let actual = (m.red_score - m.blue_score) as f64;
+        sum += (actual - predicted).powi(2);
+        count += 1;
+    }
+    if count == 0 {
+        return 1.0;
+    }
+    (sum / count as f64).sqrt().max(1.0)
+}
+
+/// Check how well a model predicts the given matches.
+///
+/// The matches that have been played are replayed in the order they were played, and every
+/// match is predicted using a model built only from the matches before it, just like it would
+/// have been at the event. OPR models skip matches with a team that has not played yet.
+pub fn backtest(matches: &[Match], mut model: Backtest) -> Calibration {
+    let mut sorted: Vec<Match> = matches.iter().filter(|m| m.is_played()).cloned().collect();
+    sorted.sort_by(|a, b| a.chronological_key().cmp(&b.chronological_key()));
+
+    let mut predicted = 0;
+    let mut brier = 0.0;
+    let mut decided = 0;
+    let mut correct = 0;
+
+    for i in 0..sorted.len() {
+        let m = &sorted[i];
+        let prediction = match &mut model {
+            Backtest::Elo(elo) => {
+                let p = predict(
+                    &m.red_teams,
+                    &m.blue_teams,
+                    &Model::Elo { elo, spread: 1.0 },
+                );
+                elo.record(m);
+                p
+            }
+            _ => {
+                let before = &sorted[..i];
+                let stats = team_stats(before);
+                if !m
+                    .red_teams
+                    .iter()
+                    .chain(m.blue_teams.iter())
+                    .all(|t| stats.contains_key(t))
+                {
+                    continue;
+                }
+                let spread = margin_spread(before, &stats);
+                let model = match model {
+                    Backtest::ComponentOpr => Model::ComponentOpr {
+                        stats: &stats,
+                        spread,
+                    },
+                    _ => Model::Opr {
+                        stats: &stats,
+                        spread,
+                    },
+                };
+                predict(&m.red_teams, &m.blue_teams, &model)
+            }
+        };
+
+        let result = if m.red_score > m.blue_score {
+            1.0
+        } else if m.red_score < m.blue_score {
+            0.0
+        } else {
+            0.5
+        };
+        predicted += 1;
+        brier += (prediction.red_win_probability - result).powi(2);
+        if result != 0.5 {
+            decided += 1;
+            if (prediction.red_win_probability > 0.5) == (result == 1.0) {
+                correct += 1;
+            }
+        }
+    }
+
+    Calibration {
+        matches: predicted,
+        brier_score: if predicted == 0 {
+            0.0
+        } else {
+            brier / predicted as f64
+        },
+        accuracy: if decided == 0 {
+            0.0
+        } else {
+            correct as f64 / decided as f64
+        },
+    }
+}
+
+/// How hard a team's qualification schedule was.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleStrength {
+    pub team_number: u32,
+    /// The number of played matches the numbers are based on.
+    pub matches: u32,
+    /// The average strength (OPR or Elo rating, depending on the model) of the team's
+    /// partners.
+    pub average_partner_strength: f64,
+    /// The average strength of the team's opponents.
+    pub average_opponent_strength: f64,
+    /// The qualifying points the team actually earned.
+    pub qualifying_points: f64,
+    /// The qualifying points the model expected the team to earn with its schedule.
+    pub expected_qualifying_points: f64,
+    /// The qualifying points the model would expect if every partner and opponent had been
+    /// an average team at the event.
+    pub neutral_qualifying_points: f64,
+    /// How much the schedule helped the team: `expected_qualifying_points -
+    /// neutral_qualifying_points`. Negative numbers mean a hard schedule.
+    pub schedule_effect: f64,
+    /// How much the team outperformed its schedule: `qualifying_points -
+    /// expected_qualifying_points`.
+    pub luck: f64,
+}
+
+/// Analyse the schedule of every team in the given played matches.
+///
+/// Winning a match earns 2 qualifying points and a tie earns 1. The expected qualifying
+/// points of a match are twice the team's win probability according to `model`.
+pub fn schedule_strength(matches: &[Match], model: &Model) -> HashMap<u32, ScheduleStrength> {
+    let mut teams: Vec<u32> = Vec::new();
+    for m in matches.iter() {
+        for team in m.red_teams.iter().chain(m.blue_teams.iter()) {
+            if !teams.contains(team) {
+                teams.push(*team);
+            }
+        }
+    }
+    let average = match teams.len() {
+        0 => 0.0,
+        n => teams.iter().map(|t| team_strength(model, *t)).sum::<f64>() / n as f64,
+    };
+
+    let mut schedules: HashMap<u32, ScheduleStrength> = HashMap::new();
+    for m in matches.iter() {
+        let alliances = [
+            (&m.red_teams, &m.blue_teams, m.red_score - m.blue_score),
+            (&m.blue_teams, &m.red_teams, m.blue_score - m.red_score),
+        ];
+        for (own, other, margin) in alliances.iter() {
+            let strengths = |teams: &[u32]| -> Vec<f64> {
+                teams.iter().map(|t| team_strength(model, *t)).collect()
+            };
+            let own_strengths = strengths(own);
+            let other_strengths = strengths(other);
+            let expected =
+                2.0 * outcome(model, &own_strengths, &other_strengths).red_win_probability;
+            let points = match margin {
+                d if *d > 0 => 2.0,
+                0 => 1.0,
+                _ => 0.0,
+            };
+
+            for (i, team) in own.iter().enumerate() {
+                let mut neutral_own = vec![average; own.len()];
+                neutral_own[i] = own_strengths[i];
+                let neutral_other = vec![average; other.len()];
+                let neutral =
+                    2.0 * outcome(model, &neutral_own, &neutral_other).red_win_probability;
+
+                let partners: Vec<f64> = own_strengths
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, s)| *s)
+                    .collect();
+
+                let entry = schedules.entry(*team).or_insert(ScheduleStrength {
+                    team_number: *team,
+                    matches: 0,
+                    average_partner_strength: 0.0,
+                    average_opponent_strength: 0.0,
+                    qualifying_points: 0.0,
+                    expected_qualifying_points: 0.0,
+                    neutral_qualifying_points: 0.0,
+                    schedule_effect: 0.0,
+                    luck: 0.0,
+                });
+                entry.matches += 1;
+                // Sums for now, turned into averages below.
+                entry.average_partner_strength += mean(&partners);
+                entry.average_opponent_strength += mean(&other_strengths);
+                entry.qualifying_points += points;
+                entry.expected_qualifying_points += expected;
+                entry.neutral_qualifying_points += neutral;
+            }
+        }
+    }
+
+    for schedule in schedules.values_mut() {
+        schedule.average_partner_strength /= schedule.matches as f64;
+        schedule.average_opponent_strength /= schedule.matches as f64;
+        schedule.schedule_effect =
+            schedule.expected_qualifying_points - schedule.neutral_qualifying_points;
+        schedule.luck = schedule.qualifying_points - schedule.expected_qualifying_points;
+    }
+    schedules
+}
+
+fn mean(values: &[f64]) -> f64 {
+    match values.len() {
+        0 => 0.0,
+        n => values.iter().sum::<f64>() / n as f64,
+    }
+}
+
+/// The scale of a logistic distribution with the given standard deviation.
+fn logistic_scale(spread: f64) -> f64 {
+    spread * 3f64.sqrt() / std::f64::consts::PI
+}
+
+fn logistic(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backtest, margin_spread, predict, schedule_strength, team_stats, Backtest, Model};
+    use crate::test_server::TestServer;
+    use crate::{Client, Elo, Match};
+
+    fn quals(red: &[u32], blue: &[u32], contribution: impl Fn(u32) -> (i32, i32, i32)) -> Match {
+        let sum = |teams: &[u32]| {
+            teams
+                .iter()
+                .map(|t| contribution(*t))
+                .fold((0, 0, 0), |acc, c| (acc.0 + c.0, acc.1 + c.1, acc.2 + c.2))
+        };
+        let (red_auto, red_tele, red_end) = sum(red);
+        let (blue_auto, blue_tele, blue_end) = sum(blue);
+        Match {
+            match_start_time: Some("2020-02-01T10:00:00Z".to_string()),
+            tournament_level: 1,
+            red_teams: red.to_vec(),
+            blue_teams: blue.to_vec(),
+            red_score: red_auto + red_tele + red_end + 10,
+            blue_score: blue_auto + blue_tele + blue_end,
+            red_penalty: 10,
+            blue_penalty: 0,
+            red_auto_score: red_auto,
+            blue_auto_score: blue_auto,
+            red_tele_score: red_tele,
+            blue_tele_score: blue_tele,
+            red_end_score: red_end,
+            blue_end_score: blue_end,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recovers_exact_contributions() {
+        let contribution = |t: u32| (t as i32 * 2, t as i32 * 10, 5);
+        let mut matches = Vec::new();
+        for a in 1..=6 {
+            for b in a + 1..=6 {
+                let others: Vec<u32> = (1..=6).filter(|t| *t != a && *t != b).collect();
+                matches.push(quals(&[a, b], &others[..2], contribution));
+            }
+        }
+
+        let stats = team_stats(&matches);
+        assert_eq!(stats.len(), 6);
+        for (team, s) in stats.iter() {
+            let (auto, tele, end) = contribution(*team);
+            assert!((s.auto_opr - auto as f64).abs() < 1e-6);
+            assert!((s.teleop_opr - tele as f64).abs() < 1e-6);
+            assert!((s.endgame_opr - end as f64).abs() < 1e-6);
+            assert!((s.np_opr - (auto + tele + end) as f64).abs() < 1e-6);
+            assert!((s.opr - s.np_opr - s.penalty_opr).abs() < 1e-6);
+            assert!((s.ccwm - (s.opr - s.dpr)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn event_stats_leave_out_playoffs() {
+        let server = TestServer::toa();
+        let event = Client::new("")
+            .with_base_url(server.url())
+            .event("1920-TX-TRQ");
+        let matches = event.matches();
+        let quals: Vec<Match> = matches
+            .iter()
+            .filter(|m| m.is_qualification())
+            .cloned()
+            .collect();
+        assert!(quals.len() < matches.len());
+        assert_eq!(event.stats(), team_stats(&quals));
+    }
+
+    #[test]
+    fn unplayed_matches_have_no_scores() {
+        let json = serde_json::json!({
+            "match_key": "1920-TX-TRQ-Q013-1",
+            "event_key": "1920-TX-TRQ",
+            "match_name": "Quals 13",
+            "tournament_level": 1,
+            "scheduled_time": "2019-12-07T12:00:00.000Z",
+            "match_start_time": null,
+            "red_score": null,
+            "blue_score": null,
+            "participants": [
+                { "team_key": "16405", "station": 11 },
+                { "team_key": "8565", "station": 12 },
+                { "team_key": "11260", "station": 21 },
+                { "team_key": "14201", "station": 22 }
+            ]
+        });
+        let m = Match::from_json(&json).unwrap();
+        assert!(!m.is_played());
+        assert_eq!((m.red_score, m.blue_teams.len()), (0, 2));
+    }
+
+    #[test]
+    fn splits_teams_that_cannot_be_told_apart() {
+        let contribution = |_| (0, 20, 0);
+        let matches = vec![quals(&[1, 2], &[3, 4], contribution)];
+        let stats = team_stats(&matches);
+        assert!((stats[&1].np_opr - 20.0).abs() < 1e-3);
+        assert!((stats[&2].np_opr - 20.0).abs() < 1e-3);
+        assert_eq!(stats[&3].matches_played, 1);
+    }
+
+    #[test]
+    fn predictions_favour_stronger_alliances() {
+        let contribution = |t: u32| (0, t as i32 * 10, 0);
+        let mut matches = Vec::new();
+        for a in 1..=6 {
+            for b in a + 1..=6 {
+                let others: Vec<u32> = (1..=6).filter(|t| *t != a && *t != b).collect();
+                matches.push(quals(&[a, b], &others[..2], contribution));
+            }
+        }
+        let stats = team_stats(&matches);
+        let spread = margin_spread(&matches, &stats);
+        assert!((1.0..10.0).contains(&spread));
+
+        let model = Model::ComponentOpr {
+            stats: &stats,
+            spread: 20.0,
+        };
+        let prediction = predict(&[5, 6], &[1, 2], &model);
+        assert!((prediction.expected_margin - 80.0).abs() < 1e-6);
+        assert!(prediction.red_win_probability > 0.95);
+
+        let elo = Elo::new();
+        let even = predict(
+            &[5, 6],
+            &[1, 2],
+            &Model::Elo {
+                elo: &elo,
+                spread: 20.0,
+            },
+        );
+        assert_eq!(even.red_win_probability, 0.5);
+        assert_eq!(even.expected_margin, 0.0);
+
+        // Teams rated thousands of points above the rest are certain, but finite, favourites.
+        let mut lopsided = Elo::new().k_factor(10000.0);
+        lopsided.record(&matches[0]);
+        let certain = predict(
+            &[1, 2],
+            &[3, 4],
+            &Model::Elo {
+                elo: &lopsided,
+                spread: 20.0,
+            },
+        );
+        assert!(certain.expected_margin.is_finite() && certain.expected_margin < 0.0);
+
+        let mut unplayed = quals(&[1, 2], &[3, 4], contribution);
+        unplayed.match_start_time = None;
+        matches.push(unplayed);
+        let calibration = backtest(&matches, Backtest::Elo(Elo::new()));
+        assert_eq!(calibration.matches, 15);
+        assert!(calibration.brier_score < 0.25);
+    }
+
+    #[test]
+    fn strong_partners_make_an_easy_schedule() {
+        let mut elo = Elo::new().k_factor(200.0);
+        elo.record(&Match {
+            match_start_time: Some("2020-02-01T10:00:00Z".to_string()),
+            red_teams: vec![9, 10],
+            blue_teams: vec![11, 12],
+            red_score: 100,
+            ..Default::default()
+        });
+        let model = Model::Elo {
+            elo: &elo,
+            spread: 20.0,
+        };
+        let matches = vec![Match {
+            red_teams: vec![1, 9],
+            blue_teams: vec![2, 11],
+            red_score: 100,
+            blue_score: 50,
+            ..Default::default()
+        }];
+
+        let schedules = schedule_strength(&matches, &model);
+        let lucky = &schedules[&1];
+        assert_eq!(lucky.matches, 1);
+        assert_eq!(lucky.average_partner_strength, elo.rating(9));
+        assert_eq!(lucky.qualifying_points, 2.0);
+        assert!(lucky.schedule_effect > 0.0);
+        assert!((lucky.luck - (2.0 - lucky.expected_qualifying_points)).abs() < 1e-9);
+        assert!(schedules[&2].schedule_effect < 0.0);
+        assert_eq!(schedules[&2].qualifying_points, 0.0);
+    }
+}
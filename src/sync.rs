@@ -0,0 +1,719 @@
+//! A local SQLite mirror of The Orange Alliance data.
+//!
+//! This module is only available with the `sqlite` feature. A [`Mirror`](struct.Mirror.html)
+//! copies the events of a season, with their teams, rankings, matches, match details and
+//! awards, into a SQLite database so they can be queried with SQL.
+//!
+//! Syncing is incremental. An event is only fetched again if its listing changed since the
+//! last sync, or if it had not ended yet when it was last synced. Each event is written in
+//! its own transaction, so a sync that is interrupted (for example by the API's rate limit)
+//! keeps every event it finished, and running it again picks up where it stopped.
+//!
+//! ```no_run
+//! use rustoa::sync::Mirror;
+//!
+//! let client = rustoa::Client::new("api_key");
+//! let mut mirror = Mirror::open("toa.sqlite", client).unwrap();
+//! match mirror.sync_season(rustoa::Season::SkyStone) {
+//!     Ok(report) => println!("{} events updated", report.synced.len()),
+//!     Err(e) => println!("Stopped after {} events: {}", e.progress.synced.len(), e),
+//! }
+//! ```
+//!
+//! # Schema
+//!
+//! | Table | Rows |
+//! |---|---|
+//! | `seasons` | `season_key`, `description` |
+//! | `events` | `event_key`, `season_key`, `region_key`, `event_type_key`, `event_name`, `start_date`, `end_date`, `city`, `state_prov`, `country`, `venue`, `advance_event` |
+//! | `teams` | `team_number`, `team_name_short`, `team_name_long`, `region_key`, `city`, `state_prov`, `country`, `rookie_year` |
+//! | `event_teams` | `event_key`, `team_number` |
+//! | `rankings` | `event_key`, `team_number` and the fields of [`Ranking`](../struct.Ranking.html) |
+//! | `matches` | `match_key`, `event_key` and the fields of [`Match`](../struct.Match.html) except the teams |
+//! | `match_teams` | `match_key`, `team_number`, `alliance` (`red` or `blue`) |
+//! | `match_details` | `match_key`, `json` (the season-specific score breakdown) |
+//! | `awards` | `event_key`, `award_key`, `team_number`, `receiver_name`, `award_name`, `award_rank` |
+//! | `sync_state` | `event_key`, `season_key`, `listing`, `synced_at` |
+
+use crate::{Client, Match, Ranking, Season};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS seasons (
+    season_key TEXT PRIMARY KEY,
+    description TEXT
+);
+CREATE TABLE IF NOT EXISTS events (
+    event_key TEXT PRIMARY KEY,
+    season_key TEXT NOT NULL,
+    region_key TEXT,
+    event_type_key TEXT,
+    event_name TEXT,
+    start_date TEXT,
+    end_date TEXT,
+    city TEXT,
+    state_prov TEXT,
+    country TEXT,
+    venue TEXT,
+    advance_event TEXT
+);
+CREATE TABLE IF NOT EXISTS teams (
+    team_number INTEGER PRIMARY KEY,
+    team_name_short TEXT,
+    team_name_long TEXT,
+    region_key TEXT,
+    city TEXT,
+    state_prov TEXT,
+    country TEXT,
+    rookie_year INTEGER
+);
+CREATE TABLE IF NOT EXISTS event_teams (
+    event_key TEXT NOT NULL,
+    team_number INTEGER NOT NULL,
+    PRIMARY KEY (event_key, team_number)
+);
+CREATE TABLE IF NOT EXISTS rankings (
+    event_key TEXT NOT NULL,
+    team_number INTEGER NOT NULL,
+    rank INTEGER,
+    rank_change INTEGER,
+    wins INTEGER,
+    losses INTEGER,
+    ties INTEGER,
+    played INTEGER,
+    disqualified INTEGER,
+    opr REAL,
+    np_opr REAL,
+    highest_qual_score REAL,
+    ranking_points REAL,
+    qualifying_points REAL,
+    tiebreaker_points REAL,
+    PRIMARY KEY (event_key, team_number)
+);
+CREATE TABLE IF NOT EXISTS matches (
+    match_key TEXT PRIMARY KEY,
+    event_key TEXT NOT NULL,
+    match_name TEXT,
+    tournament_level INTEGER,
+    scheduled_time TEXT,
+    match_start_time TEXT,
+    red_score INTEGER,
+    blue_score INTEGER,
+    red_penalty INTEGER,
+    blue_penalty INTEGER,
+    red_auto_score INTEGER,
+    blue_auto_score INTEGER,
+    red_tele_score INTEGER,
+    blue_tele_score INTEGER,
+    red_end_score INTEGER,
+    blue_end_score INTEGER
+);
+CREATE TABLE IF NOT EXISTS match_teams (
+    match_key TEXT NOT NULL,
+    team_number INTEGER NOT NULL,
+    alliance TEXT NOT NULL,
+    PRIMARY KEY (match_key, team_number)
+);
+CREATE TABLE IF NOT EXISTS match_details (
+    match_key TEXT PRIMARY KEY,
+    event_key TEXT NOT NULL,
+    json TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS awards (
+    event_key TEXT NOT NULL,
+    award_key TEXT,
+    team_number INTEGER,
+    receiver_name TEXT,
+    award_name TEXT,
+    award_rank INTEGER
+);
+CREATE TABLE IF NOT EXISTS sync_state (
+    event_key TEXT PRIMARY KEY,
+    season_key TEXT NOT NULL,
+    listing TEXT NOT NULL,
+    synced_at TEXT NOT NULL
+);
+";
+
+/// The events a sync fetched and the ones it left alone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncReport {
+    /// The keys of the events that were fetched and written.
+    pub synced: Vec<String>,
+    /// The keys of the events that had not changed since the last sync.
+    pub skipped: Vec<String>,
+    /// The synced events with ranking or match rows that could not be read and were left
+    /// out, with how many rows were left out.
+    pub malformed: Vec<(String, usize)>,
+}
+
+/// Why a sync stopped.
+#[derive(Debug)]
+pub enum SyncErrorKind {
+    /// The API answered `429 Too Many Requests`.
+    RateLimited,
+    /// The API answered with another unsuccessful status code.
+    Api(u16),
+    /// The API could not be reached.
+    Transport(String),
+    /// The API returned invalid JSON.
+    Json(serde_json::Error),
+    /// The database could not be written.
+    Database(Box<rusqlite::Error>),
+}
+
+/// A sync that stopped before every event was mirrored.
+///
+/// The events in `progress` were written and will be skipped by the next sync, unless they
+/// change again.
+#[derive(Debug)]
+pub struct SyncError {
+    pub kind: SyncErrorKind,
+    pub progress: SyncReport,
+}
+
+impl SyncError {
+    /// Whether the sync stopped because of the API's rate limit, in which case it can be
+    /// run again later to resume.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self.kind, SyncErrorKind::RateLimited)
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            SyncErrorKind::RateLimited => write!(f, "The API rate limit was reached"),
+            SyncErrorKind::Api(status) => write!(f, "The API returned {}", status),
+            SyncErrorKind::Transport(e) => write!(f, "The API could not be reached: {}", e),
+            SyncErrorKind::Json(e) => write!(f, "The API returned invalid JSON: {}", e),
+            SyncErrorKind::Database(e) => write!(f, "The database could not be written: {}", e),
+        }
+    }
+}
+
+impl Error for SyncError {}
+
+impl From<rusqlite::Error> for SyncErrorKind {
+    fn from(e: rusqlite::Error) -> SyncErrorKind {
+        SyncErrorKind::Database(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for SyncErrorKind {
+    fn from(e: serde_json::Error) -> SyncErrorKind {
+        SyncErrorKind::Json(e)
+    }
+}
+
+/// A SQLite database mirroring The Orange Alliance.
+pub struct Mirror {
+    conn: Connection,
+    client: Client,
+}
+
+impl Mirror {
+    /// Open (and create if needed) a mirror in the given SQLite file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the database file.
+    /// * `client` - The client used to fetch data from the API.
+    pub fn open<P: AsRef<Path>>(path: P, client: Client) -> rusqlite::Result<Mirror> {
+        Mirror::from_connection(Connection::open(path)?, client)
+    }
+
+    /// Create a mirror in an open database, such as one made with
+    /// `Connection::open_in_memory`.
+    pub fn from_connection(conn: Connection, client: Client) -> rusqlite::Result<Mirror> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Mirror { conn, client })
+    }
+
+    /// The database connection, to run queries on.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Mirror every event of a season, skipping the ones that have not changed since the
+    /// last sync.
+    ///
+    /// Events are synced in the order they start. If the sync stops early, the error holds
+    /// the events that were written before it stopped.
+    pub fn sync_season(&mut self, season: Season) -> Result<SyncReport, SyncError> {
+        let mut progress = SyncReport::default();
+        match self.sync_events(&season, &mut progress) {
+            Ok(()) => Ok(progress),
+            Err(kind) => Err(SyncError { kind, progress }),
+        }
+    }
+
+    fn sync_events(
+        &mut self,
+        season: &Season,
+        progress: &mut SyncReport,
+    ) -> Result<(), SyncErrorKind> {
+        let season_key = season.value().to_string();
+        let description = season.to_string().replace("Season::", "");
+        self.conn.execute(
+            "INSERT OR REPLACE INTO seasons (season_key, description) VALUES (?1, ?2)",
+            params![season_key, description],
+        )?;
+
+        let mut listings = self.get(&format!("/event?season_key={}", season_key))?;
+        listings.sort_by(|a, b| {
+            (text(a, "start_date"), text(a, "event_key"))
+                .cmp(&(text(b, "start_date"), text(b, "event_key")))
+        });
+
+        for listing in listings.iter() {
+            let event_key = match listing["event_key"].as_str() {
+                Some(k) => k.to_string(),
+                None => continue,
+            };
+            if self.is_current(&event_key, listing)? {
+                progress.skipped.push(event_key);
+            } else {
+                let malformed = self.sync_event(&event_key, &season_key, listing)?;
+                if malformed > 0 {
+                    progress.malformed.push((event_key.clone(), malformed));
+                }
+                progress.synced.push(event_key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the stored copy of an event is up to date: its listing has not changed, and
+    /// it was last synced after the day the event ended.
+    fn is_current(&self, event_key: &str, listing: &Value) -> Result<bool, SyncErrorKind> {
+        let state: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT listing, synced_at FROM sync_state WHERE event_key = ?1",
+                params![event_key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (stored, synced_at) = match state {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+        let stored: Value = serde_json::from_str(&stored)?;
+        let end_date = text(listing, "end_date");
+        Ok(stored == *listing && date(&synced_at) > date(&end_date))
+    }
+
+    /// Fetch everything about an event, then replace the stored copy in one transaction.
+    ///
+    /// It returns the number of ranking and match rows that could not be read.
+    fn sync_event(
+        &mut self,
+        event_key: &str,
+        season_key: &str,
+        listing: &Value,
+    ) -> Result<usize, SyncErrorKind> {
+        let teams = self.get(&format!("/event/{}/teams", event_key))?;
+        let rankings = self.get(&format!("/event/{}/rankings", event_key))?;
+        let matches = self.get(&format!("/event/{}/matches", event_key))?;
+        let details = self.get(&format!("/event/{}/matches/details", event_key))?;
+        let awards = self.get(&format!("/event/{}/awards", event_key))?;
+
+        let tx = self.conn.transaction()?;
+        write_event(&tx, event_key, season_key, listing)?;
+        for table in ["event_teams", "rankings", "match_details", "awards"].iter() {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE event_key = ?1", table),
+                params![event_key],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM match_teams WHERE match_key IN \
+             (SELECT match_key FROM matches WHERE event_key = ?1)",
+            params![event_key],
+        )?;
+        tx.execute(
+            "DELETE FROM matches WHERE event_key = ?1",
+            params![event_key],
+        )?;
+
+        for participant in teams.iter() {
+            let team = &participant["team"];
+            let team_number = match team["team_number"]
+                .as_u64()
+                .or_else(|| participant["team_number"].as_u64())
+            {
+                Some(n) => n,
+                None => continue,
+            };
+            write_team(&tx, team_number, team)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO event_teams (event_key, team_number) VALUES (?1, ?2)",
+                params![event_key, team_number],
+            )?;
+        }
+        let mut malformed = 0;
+        for row in rankings.iter() {
+            match Ranking::from_json(row) {
+                Some(ranking) => {
+                    write_ranking(&tx, event_key, &ranking)?;
+                }
+                None => malformed += 1,
+            }
+        }
+        for row in matches.iter() {
+            match Match::from_json(row) {
+                Some(m) => write_match(&tx, &m)?,
+                None => malformed += 1,
+            }
+        }
+        for detail in details.iter() {
+            if let Some(match_key) = detail["match_key"].as_str() {
+                tx.execute(
+                    "INSERT OR REPLACE INTO match_details (match_key, event_key, json) \
+                     VALUES (?1, ?2, ?3)",
+                    params![match_key, event_key, detail.to_string()],
+                )?;
+            }
+        }
+        for award in awards.iter() {
+            tx.execute(
+                "INSERT INTO awards \
+                 (event_key, award_key, team_number, receiver_name, award_name, award_rank) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    event_key,
+                    award["award_key"].as_str(),
+                    award["team_key"]
+                        .as_str()
+                        .and_then(|k| k.parse::<u32>().ok()),
+                    award["receiver_name"].as_str(),
+                    award["award_name"].as_str(),
+                    award["award_rank"].as_i64(),
+                ],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO sync_state (event_key, season_key, listing, synced_at) \
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                event_key,
+                season_key,
+                listing.to_string(),
+                timestamp(SystemTime::now())
+            ],
+        )?;
+        tx.commit()?;
+        Ok(malformed)
+    }
+
+    /// Fetch a list from the API. Missing lists, which the API answers with a `404`, are
+    /// empty.
+    fn get(&self, path: &str) -> Result<Vec<Value>, SyncErrorKind> {
+        let resp = match self.client.request(path) {
+            Ok(r) => r,
+            Err(e) => return Err(SyncErrorKind::Transport(e.to_string())),
+        };
+        match resp.status {
+            429 => return Err(SyncErrorKind::RateLimited),
+            404 => return Ok(Vec::new()),
+            status if !resp.is_success() => return Err(SyncErrorKind::Api(status)),
+            _ => {}
+        }
+        match serde_json::from_str(&resp.body)? {
+            Value::Array(values) => Ok(values),
+            value => Ok(vec![value]),
+        }
+    }
+}
+
+fn write_event(
+    tx: &Transaction,
+    event_key: &str,
+    season_key: &str,
+    listing: &Value,
+) -> rusqlite::Result<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO events (event_key, season_key, region_key, event_type_key, \
+         event_name, start_date, end_date, city, state_prov, country, venue, advance_event) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            event_key,
+            season_key,
+            listing["region_key"].as_str(),
+            listing["event_type_key"].as_str(),
+            listing["event_name"].as_str(),
+            listing["start_date"].as_str(),
+            listing["end_date"].as_str(),
+            listing["city"].as_str(),
+            listing["state_prov"].as_str(),
+            listing["country"].as_str(),
+            listing["venue"].as_str(),
+            listing["advance_event"].as_str(),
+        ],
+    )
+}
+
+fn write_team(tx: &Transaction, team_number: u64, team: &Value) -> rusqlite::Result<usize> {
+    // Event team lists only include some of a team's details, so keep the ones already
+    // stored.
+    tx.execute(
+        "INSERT INTO teams (team_number, team_name_short, team_name_long, region_key, city, \
+         state_prov, country, rookie_year) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) \
+         ON CONFLICT(team_number) DO UPDATE SET \
+         team_name_short = coalesce(excluded.team_name_short, team_name_short), \
+         team_name_long = coalesce(excluded.team_name_long, team_name_long), \
+         region_key = coalesce(excluded.region_key, region_key), \
+         city = coalesce(excluded.city, city), \
+         state_prov = coalesce(excluded.state_prov, state_prov), \
+         country = coalesce(excluded.country, country), \
+         rookie_year = coalesce(excluded.rookie_year, rookie_year)",
+        params![
+            team_number,
+            team["team_name_short"].as_str(),
+            team["team_name_long"].as_str(),
+            team["region_key"].as_str(),
+            team["city"].as_str(),
+            team["state_prov"].as_str(),
+            team["country"].as_str(),
+            team["rookie_year"].as_i64(),
+        ],
+    )
+}
+
+fn write_ranking(tx: &Transaction, event_key: &str, r: &Ranking) -> rusqlite::Result<usize> {
+    tx.execute(
+        "INSERT OR REPLACE INTO rankings (event_key, team_number, rank, rank_change, wins, \
+         losses, ties, played, disqualified, opr, np_opr, highest_qual_score, ranking_points, \
+         qualifying_points, tiebreaker_points) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            event_key,
+            r.team_number,
+            r.rank,
+            r.rank_change,
+            r.wins,
+            r.losses,
+            r.ties,
+            r.played,
+            r.disqualified,
+            r.opr,
+            r.np_opr,
+            r.highest_qual_score,
+            r.ranking_points,
+            r.qualifying_points,
+            r.tiebreaker_points,
+        ],
+    )
+}
+
+fn write_match(tx: &Transaction, m: &Match) -> rusqlite::Result<()> {
+    // Matches that have not been played have no scores yet.
+    let score = |s: i32| Some(s).filter(|_| m.is_played());
+    tx.execute(
+        "INSERT OR REPLACE INTO matches (match_key, event_key, match_name, tournament_level, \
+         scheduled_time, match_start_time, red_score, blue_score, red_penalty, blue_penalty, \
+         red_auto_score, blue_auto_score, red_tele_score, blue_tele_score, red_end_score, \
+         blue_end_score) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            m.match_key,
+            m.event_key,
+            m.match_name,
+            m.tournament_level,
+            m.scheduled_time,
+            m.match_start_time,
+            score(m.red_score),
+            score(m.blue_score),
+            score(m.red_penalty),
+            score(m.blue_penalty),
+            score(m.red_auto_score),
+            score(m.blue_auto_score),
+            score(m.red_tele_score),
+            score(m.blue_tele_score),
+            score(m.red_end_score),
+            score(m.blue_end_score),
+        ],
+    )?;
+    for (alliance, teams) in [("red", &m.red_teams), ("blue", &m.blue_teams)].iter() {
+        for team in teams.iter() {
+            tx.execute(
+                "INSERT OR REPLACE INTO match_teams (match_key, team_number, alliance) \
+                 VALUES (?1, ?2, ?3)",
+                params![m.match_key, team, alliance],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn text(json: &Value, key: &str) -> String {
+    json[key].as_str().unwrap_or("").to_string()
+}
+
+/// The `YYYY-MM-DD` part of an RFC 3339 timestamp.
+fn date(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+/// Format a time as an RFC 3339 timestamp in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Convert days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timestamp, Mirror};
+    use crate::test_server::{fixture_file, fixtures_dir};
+    use crate::{Client, FixtureTransport, Season};
+    use rusqlite::Connection;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// A transport serving the fixture events, listed with the given end dates.
+    fn transport(end_dates: &[&str], rate_limited: Option<&str>) -> FixtureTransport {
+        let mut listing = Vec::new();
+        let mut transport = FixtureTransport::new();
+        for (key, end_date) in ["1920-TX-TRQ", "1920-TX-PLQ"].iter().zip(end_dates) {
+            let path = format!("/event/{}", key);
+            let body = std::fs::read_to_string(fixture_file(&fixtures_dir(), &path)).unwrap();
+            let mut event: serde_json::Value = serde_json::from_str(&body).unwrap();
+            event[0]["end_date"] = serde_json::Value::from(*end_date);
+            listing.push(event[0].clone());
+            for part in ["teams", "rankings", "matches"].iter() {
+                let path = format!("/event/{}/{}", key, part);
+                let body = std::fs::read_to_string(fixture_file(&fixtures_dir(), &path)).unwrap();
+                transport = transport.with(&path, &body);
+            }
+            transport = transport
+                .with_status(&format!("/event/{}/matches/details", key), 404, "{}")
+                .with(&format!("/event/{}/awards", key), "[]");
+            if rate_limited == Some(key) {
+                transport = transport.with_status(&format!("/event/{}/rankings", key), 429, "");
+            }
+        }
+        transport.with(
+            "/event?season_key=1920",
+            &serde_json::Value::from(listing).to_string(),
+        )
+    }
+
+    fn mirror(transport: FixtureTransport, conn: Connection) -> Mirror {
+        let client = Client::new("api_key").with_transport(transport);
+        Mirror::from_connection(conn, client).unwrap()
+    }
+
+    fn count(mirror: &Mirror, sql: &str) -> i64 {
+        mirror
+            .connection()
+            .query_row(sql, [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn resumes_after_rate_limit_and_skips_finished_events() {
+        let dir = std::env::temp_dir().join(format!("rustoa-sync-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("toa.sqlite");
+        let _ = std::fs::remove_file(&path);
+        let ended = ["2019-12-07T00:00:00.000Z", "2020-01-11T00:00:00.000Z"];
+
+        // TRQ is written before PLQ hits the rate limit.
+        let mut first = mirror(
+            transport(&ended, Some("1920-TX-PLQ")),
+            Connection::open(&path).unwrap(),
+        );
+        let err = first.sync_season(Season::SkyStone).unwrap_err();
+        assert!(err.is_rate_limited());
+        assert_eq!(err.progress.synced, vec!["1920-TX-TRQ"]);
+        assert_eq!(count(&first, "SELECT count(*) FROM events"), 1);
+        assert_eq!(
+            count(
+                &first,
+                "SELECT count(*) FROM rankings WHERE event_key = '1920-TX-PLQ'"
+            ),
+            0
+        );
+        drop(first);
+
+        let mut second = mirror(transport(&ended, None), Connection::open(&path).unwrap());
+        let report = second.sync_season(Season::SkyStone).unwrap();
+        assert_eq!(report.skipped, vec!["1920-TX-TRQ"]);
+        assert_eq!(report.synced, vec!["1920-TX-PLQ"]);
+        assert_eq!(
+            count(
+                &second,
+                "SELECT rank FROM rankings WHERE event_key = '1920-TX-TRQ' AND team_number = 16405"
+            ),
+            2
+        );
+        assert_eq!(
+            count(
+                &second,
+                "SELECT count(*) FROM match_teams WHERE match_key = '1920-TX-TRQ-Q001-1'"
+            ),
+            4
+        );
+        assert_eq!(count(&second, "SELECT count(*) FROM teams"), 10);
+
+        // An event that has not ended is fetched again, even if its listing is the same.
+        let ongoing = ["2019-12-07T00:00:00.000Z", "2999-01-01T00:00:00.000Z"];
+        let mut third = mirror(transport(&ongoing, None), Connection::open(&path).unwrap());
+        third.sync_season(Season::SkyStone).unwrap();
+        let report = third.sync_season(Season::SkyStone).unwrap();
+        assert_eq!(report.synced, vec!["1920-TX-PLQ"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_rows_that_cannot_be_read() {
+        let ended = ["2019-12-07T00:00:00.000Z", "2020-01-11T00:00:00.000Z"];
+        let path = "/event/1920-TX-TRQ/matches";
+        let body = std::fs::read_to_string(fixture_file(&fixtures_dir(), path)).unwrap();
+        let mut matches: serde_json::Value = serde_json::from_str(&body).unwrap();
+        matches[0]["match_key"] = serde_json::Value::Null;
+        let transport = transport(&ended, None).with(path, &matches.to_string());
+
+        let mut mirror = mirror(transport, Connection::open_in_memory().unwrap());
+        let report = mirror.sync_season(Season::SkyStone).unwrap();
+        assert_eq!(report.malformed, vec![("1920-TX-TRQ".to_string(), 1)]);
+        assert_eq!(
+            count(
+                &mirror,
+                "SELECT count(*) FROM matches WHERE event_key = '1920-TX-TRQ'"
+            ),
+            13
+        );
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_575_730_800);
+        assert_eq!(timestamp(time), "2019-12-07T15:00:00Z");
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+}
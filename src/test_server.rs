@@ -0,0 +1,193 @@
+//! A local stand-in for The Orange Alliance API used by the test suite.
+//!
+//! It serves the responses in `tests/fixtures/toa`. These are synthetic: they were written
+//! by hand in the shape of TOA's responses, not captured from the live API, so the teams,
+//! scores and statistics in them are made up, and the tests assert against them. Live
+//! responses are recorded into `tests/fixtures/toa-recorded` instead, where they cannot
+//! change what the tests expect.
+//!
+//! The response for a request path is stored at the same path with a `.json` extension
+//! (`/team/16405/wlt` is `team/16405/wlt.json`), and paths ending in a slash are stored
+//! as `index.json` in that directory.
+//!
+//! It can also serve the stand-in FTC Events API responses in `tests/fixtures/ftc-events`,
+//! and the FTCScout GraphQL responses in `tests/fixtures/ftcscout`. A GraphQL query is
+//! served from the path of its operation and variables, so the `Team` query for team 16405
+//! is `graphql/Team/number=16405.json`. The text of each query is kept, so tests can
+//! compare it with the snapshots in `tests/fixtures/ftcscout/queries`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct TestServer {
+    server: Arc<tiny_http::Server>,
+    url: String,
+    hits: Arc<Mutex<HashMap<String, usize>>>,
+    queries: Arc<Mutex<HashMap<String, String>>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/toa")
+}
+
+/// Where live TOA responses are recorded, for comparing with the synthetic fixtures.
+pub fn recorded_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/toa-recorded")
+}
+
+/// Write the text of a GraphQL query to the snapshot of its operation in `dir`.
+///
+/// # Panics
+///
+/// This function panics if the operation has no name, since its snapshot would have none
+/// either, or if the snapshot cannot be written.
+pub fn write_query_snapshot(dir: &Path, operation: &str, query: &str) {
+    if operation.is_empty() {
+        panic!("Something went wrong: a query snapshot needs an operation name");
+    }
+    let file = dir.join(format!("{}.graphql", operation));
+    if let Err(e) = std::fs::write(file, format!("{}\n", query)) {
+        panic!("Something went wrong: {}", e);
+    }
+}
+
+/// The file holding the fixture response for a request path. A query string is stored as
+/// one more directory, so `/2019/teams?teamNumber=16405` is `2019/teams/teamNumber=16405.json`.
+pub fn fixture_file(root: &Path, path: &str) -> PathBuf {
+    let path = path.trim_start_matches('/').replace('?', "/");
+    let path = &path[..];
+    if path.is_empty() || path.ends_with('/') {
+        root.join(path).join("index.json")
+    } else {
+        root.join(format!("{}.json", path))
+    }
+}
+
+/// Every request path that has a fixture response in `root`.
+pub fn fixture_paths(root: &Path) -> Vec<String> {
+    fn walk(root: &Path, dir: &Path, paths: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let file = entry.unwrap().path();
+            if file.is_dir() {
+                walk(root, &file, paths);
+                continue;
+            }
+            let rel = file
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            let path = match rel.strip_suffix("index.json") {
+                Some(dir) => format!("/{}", dir),
+                None => format!("/{}", rel.trim_end_matches(".json")),
+            };
+            paths.push(path);
+        }
+    }
+    let mut paths = Vec::new();
+    walk(root, root, &mut paths);
+    paths.sort();
+    paths
+}
+
+/// The request path a GraphQL query is stored under: its operation name, then its
+/// variables in alphabetical order as a query string.
+fn graphql_path(path: &str, body: &str) -> String {
+    let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let operation = json["operationName"].as_str().unwrap_or("");
+    let variables: Vec<String> = json["variables"]
+        .as_object()
+        .map(|vars| {
+            vars.iter()
+                .map(|(k, v)| match v.as_str() {
+                    Some(s) => format!("{}={}", k, s),
+                    None => format!("{}={}", k, v),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    format!("{}/{}?{}", path, operation, variables.join("&"))
+}
+
+impl TestServer {
+    /// Serve the synthetic TOA responses.
+    pub fn toa() -> TestServer {
+        TestServer::serve(fixtures_dir())
+    }
+
+    /// Serve the stand-in FTC Events API responses.
+    pub fn ftc_events() -> TestServer {
+        TestServer::serve(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ftc-events"))
+    }
+
+    /// Serve the stand-in FTCScout GraphQL responses.
+    pub fn ftcscout() -> TestServer {
+        TestServer::serve(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ftcscout"))
+    }
+
+    pub fn serve(root: PathBuf) -> TestServer {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+        let url = format!("http://{}", server.server_addr());
+        let hits = Arc::new(Mutex::new(HashMap::new()));
+        let queries = Arc::new(Mutex::new(HashMap::new()));
+
+        let (s, h, q) = (server.clone(), hits.clone(), queries.clone());
+        let thread = thread::spawn(move || {
+            for mut request in s.incoming_requests() {
+                let mut path = request.url().to_string();
+                if *request.method() == tiny_http::Method::Post {
+                    let mut body = String::new();
+                    let _ = request.as_reader().read_to_string(&mut body);
+                    path = graphql_path(&path, &body);
+                    let json: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+                    let operation = json["operationName"].as_str().filter(|o| !o.is_empty());
+                    if let Some(operation) = operation {
+                        let query = json["query"].as_str().unwrap_or("").to_string();
+                        q.lock().unwrap().insert(operation.to_string(), query);
+                    }
+                }
+                *h.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+                let response = match std::fs::read_to_string(fixture_file(&root, &path)) {
+                    Ok(body) => tiny_http::Response::from_string(body).with_status_code(200),
+                    Err(_) => tiny_http::Response::from_string("[]").with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+
+        TestServer {
+            server,
+            url,
+            hits,
+            queries,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The number of requests the server has received for a path.
+    pub fn hits(&self, path: &str) -> usize {
+        *self.hits.lock().unwrap().get(path).unwrap_or(&0)
+    }
+
+    /// The text of the last GraphQL query the server received for an operation.
+    pub fn query(&self, operation: &str) -> Option<String> {
+        self.queries.lock().unwrap().get(operation).cloned()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        // The listening socket is only closed once the last handle to the server is gone.
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! The HTTP layer used to talk to the API.
+//!
+//! [`Client`](../struct.Client.html) sends every request through a [`Transport`](trait.Transport.html).
+//! By default this is a [`ReqwestTransport`](struct.ReqwestTransport.html), but any type that
+//! implements the trait can be plugged in with
+//! [`Client::with_transport`](../struct.Client.html#method.with_transport).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A request made by the client.
+#[derive(Clone, Debug)]
+pub struct TransportRequest {
+    /// The full URL, made of the client's base URL and the request path.
+    pub url: String,
+    /// The request path, such as `/event/1920-TX-TRQ/rankings`.
+    pub path: String,
+    /// The headers to send, including the API key.
+    pub headers: Vec<(String, String)>,
+}
+
+/// The status code and body of a response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The raw response body.
+    pub body: String,
+    /// The response headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+}
+
+impl TransportResponse {
+    /// Whether the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Whether the status code is in the `5xx` range.
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.status)
+    }
+
+    /// The value of a header, if the response has it.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| &v[..])
+    }
+
+    /// How long the server asked to wait before trying again, from the `Retry-After` header
+    /// in seconds.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let seconds = self.header("retry-after")?.trim().parse::<f64>().ok()?;
+        if seconds.is_finite() && seconds >= 0.0 {
+            Some(Duration::from_secs_f64(seconds))
+        } else {
+            None
+        }
+    }
+}
+
+/// Something that can send a request and return the response.
+///
+/// An error should only be returned when the server could not be reached. Responses with
+/// an error status are returned as a normal [`TransportResponse`](struct.TransportResponse.html).
+///
+/// ```
+/// use rustoa::{Transport, TransportRequest, TransportResponse};
+///
+/// #[derive(Debug)]
+/// struct Logged(rustoa::ReqwestTransport);
+///
+/// impl Transport for Logged {
+///     fn get(
+///         &self,
+///         request: &TransportRequest,
+///     ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+///         println!("GET {}", request.path);
+///         self.0.get(request)
+///     }
+/// }
+///
+/// let client = rustoa::Client::new("api_key").with_transport(Logged(Default::default()));
+/// ```
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// Send a GET request.
+    fn get(
+        &self,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>>;
+
+    /// Send a POST request with a JSON body.
+    ///
+    /// Only backends that need it, such as the
+    /// [`FtcScout`](../backend/struct.FtcScout.html) GraphQL API, send POST requests. By
+    /// default this returns an error.
+    fn post(
+        &self,
+        request: &TransportRequest,
+        body: &str,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        let _ = body;
+        Err(format!(
+            "This transport cannot send a POST request to {}",
+            request.path
+        )
+        .into())
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn get(
+        &self,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        (**self).get(request)
+    }
+
+    fn post(
+        &self,
+        request: &TransportRequest,
+        body: &str,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        (**self).post(request, body)
+    }
+}
+
+/// The default transport, backed by a blocking `reqwest` client.
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a new transport with a default `reqwest` client.
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::default()
+    }
+
+    /// Create a new transport that uses an existing `reqwest` client, for example one with
+    /// a custom timeout or proxy.
+    pub fn with_client(client: reqwest::blocking::Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn get(
+        &self,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        self.send(self.client.get(&request.url[..]), request)
+    }
+
+    fn post(
+        &self,
+        request: &TransportRequest,
+        body: &str,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        let builder = self.client.post(&request.url[..]).body(body.to_string());
+        self.send(builder, request)
+    }
+}
+
+impl ReqwestTransport {
+    fn send(
+        &self,
+        mut builder: reqwest::blocking::RequestBuilder,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        for (name, value) in request.headers.iter() {
+            builder = builder.header(&name[..], &value[..]);
+        }
+        let resp = builder.send()?;
+        let status = resp.status().as_u16();
+        let headers = resp
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = resp.text()?;
+        Ok(TransportResponse {
+            status,
+            body,
+            headers,
+        })
+    }
+}
+
+/// A transport that serves fixed responses from memory without touching the network.
+///
+/// Requests for paths without a response fail as if the API could not be reached.
+///
+/// ```
+/// use rustoa::FixtureTransport;
+///
+/// let transport = FixtureTransport::new().with("/", r#"{"version": "3.7.0"}"#);
+/// let client = rustoa::Client::new("api_key").with_transport(transport);
+/// assert_eq!(client.api_version(), "3.7.0");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FixtureTransport {
+    responses: HashMap<String, TransportResponse>,
+}
+
+impl FixtureTransport {
+    /// Create a transport with no responses.
+    pub fn new() -> FixtureTransport {
+        FixtureTransport::default()
+    }
+
+    /// Serve `body` with a `200` status for the request path `path`.
+    pub fn with(self, path: &str, body: &str) -> FixtureTransport {
+        self.with_status(path, 200, body)
+    }
+
+    /// Serve `body` with the given status for the request path `path`.
+    pub fn with_status(self, path: &str, status: u16, body: &str) -> FixtureTransport {
+        self.with_response(
+            path,
+            TransportResponse {
+                status,
+                body: body.to_string(),
+                headers: Vec::new(),
+            },
+        )
+    }
+
+    /// Serve a response with headers for the request path `path`.
+    pub fn with_response(mut self, path: &str, response: TransportResponse) -> FixtureTransport {
+        self.responses.insert(path.to_string(), response);
+        self
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn get(
+        &self,
+        request: &TransportRequest,
+    ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+        match self.responses.get(&request.path) {
+            Some(resp) => Ok(resp.clone()),
+            None => Err(format!("No fixture for {}", request.path).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FixtureTransport, Transport, TransportRequest};
+
+    #[test]
+    fn fixture_transport_serves_by_path() {
+        let transport = FixtureTransport::new()
+            .with("/team/16405/wlt", "[]")
+            .with_status("/team/1/wlt", 404, "{}");
+        let request = |path: &str| TransportRequest {
+            url: format!("http://localhost{}", path),
+            path: path.to_string(),
+            headers: Vec::new(),
+        };
+
+        let resp = transport.get(&request("/team/16405/wlt")).unwrap();
+        assert!(resp.is_success());
+        assert_eq!(resp.body, "[]");
+        assert_eq!(transport.get(&request("/team/1/wlt")).unwrap().status, 404);
+        assert!(transport.get(&request("/team/2/wlt")).is_err());
+    }
+}
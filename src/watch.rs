@@ -0,0 +1,454 @@
+//! Watching an event for changes while it is being played.
+//!
+//! A [`Watcher`](struct.Watcher.html) polls the matches, rankings, alliances and awards of an
+//! event, compares them with what it saw the poll before, and yields a
+//! [`Change`](enum.Change.html) for everything that is new. It skips the caches of the client,
+//! so every poll sees the latest data.
+//!
+//! Polling is adaptive. The watcher polls at the base interval while things are changing,
+//! slows down (up to a maximum) while nothing is, and backs off when the API rate limits it
+//! or cannot be reached.
+
+use crate::{Event, Match, Ranking};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// An award given at an event.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Award {
+    /// The key of the award, such as `INS2`.
+    pub award_key: String,
+    pub award_name: String,
+    /// The team that won the award, if it was given to a team.
+    pub team_number: Option<u32>,
+    /// The person who won the award, if it was given to a person.
+    pub receiver_name: Option<String>,
+}
+
+/// Something that changed at an event between two polls.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change {
+    /// A match was played and its score posted.
+    MatchScored(Box<Match>),
+    /// The score of a match that had already been posted was changed.
+    ScoreCorrected {
+        before: Box<Match>,
+        after: Box<Match>,
+    },
+    /// A team moved in the qualification rankings. `from` is `None` the first time the team
+    /// is ranked.
+    RankChanged {
+        team_number: u32,
+        from: Option<u32>,
+        to: u32,
+    },
+    /// An alliance was formed, or a team was added to it.
+    AllianceSelected { alliance: String, teams: Vec<u32> },
+    /// An award was posted.
+    AwardPosted(Award),
+}
+
+/// Everything the watcher knows about an event at one point in time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub matches: HashMap<String, Match>,
+    /// The rank of each team.
+    pub ranks: HashMap<u32, u32>,
+    /// The teams of each alliance, by alliance name.
+    pub alliances: HashMap<String, Vec<u32>>,
+    pub awards: Vec<Award>,
+}
+
+impl Snapshot {
+    /// The changes needed to go from `self` to `next`, with matches in the order of their
+    /// keys and teams in order of their new rank.
+    pub fn diff(&self, next: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        let mut keys: Vec<&String> = next.matches.keys().collect();
+        keys.sort();
+        for key in keys {
+            let after = &next.matches[key];
+            if !after.is_played() {
+                continue;
+            }
+            match self.matches.get(key) {
+                Some(before) if before.is_played() => {
+                    let score =
+                        |m: &Match| (m.red_score, m.blue_score, m.red_penalty, m.blue_penalty);
+                    if score(before) != score(after) {
+                        changes.push(Change::ScoreCorrected {
+                            before: Box::new(before.clone()),
+                            after: Box::new(after.clone()),
+                        });
+                    }
+                }
+                _ => changes.push(Change::MatchScored(Box::new(after.clone()))),
+            }
+        }
+
+        let mut ranks: Vec<(&u32, &u32)> = next.ranks.iter().collect();
+        ranks.sort_by_key(|(team, rank)| (**rank, **team));
+        for (team, rank) in ranks {
+            let from = self.ranks.get(team).cloned();
+            if from != Some(*rank) {
+                changes.push(Change::RankChanged {
+                    team_number: *team,
+                    from,
+                    to: *rank,
+                });
+            }
+        }
+
+        let mut alliances: Vec<&String> = next.alliances.keys().collect();
+        alliances.sort();
+        for name in alliances {
+            let teams = &next.alliances[name];
+            if self.alliances.get(name) != Some(teams) && !teams.is_empty() {
+                changes.push(Change::AllianceSelected {
+                    alliance: name.clone(),
+                    teams: teams.clone(),
+                });
+            }
+        }
+
+        for award in next.awards.iter() {
+            if !self.awards.contains(award) {
+                changes.push(Change::AwardPosted(award.clone()));
+            }
+        }
+        changes
+    }
+}
+
+/// Polls an event and yields its changes.
+///
+/// You can get a watcher with [`Event::watch`](../struct.Event.html#method.watch). It is an
+/// iterator that blocks until the next change, and never ends:
+///
+/// ```no_run
+/// use rustoa::watch::Change;
+/// use std::time::Duration;
+///
+/// let client = rustoa::Client::new("api_key");
+/// for change in client.event("1920-TX-TRQ").watch(Duration::from_secs(30)) {
+///     if let Change::MatchScored(m) = change {
+///         println!("{}: {} - {}", m.match_name, m.red_score, m.blue_score);
+///     }
+/// }
+/// ```
+///
+/// The first poll only records the state of the event. Changes are reported from the second
+/// poll on.
+#[derive(Debug)]
+pub struct Watcher {
+    event: Event,
+    base_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+    snapshot: Option<Snapshot>,
+    pending: VecDeque<Change>,
+}
+
+impl Watcher {
+    pub(crate) fn new(event: Event, interval: Duration) -> Watcher {
+        Watcher {
+            event,
+            base_interval: interval,
+            max_interval: interval * 4,
+            interval,
+            snapshot: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The longest the watcher will wait between polls. By default this is four times the
+    /// base interval.
+    pub fn max_interval(mut self, max_interval: Duration) -> Watcher {
+        self.max_interval = max_interval.max(self.base_interval);
+        self
+    }
+
+    /// How long the watcher will wait before its next poll.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The state of the event at the last successful poll.
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Poll the event once and return what changed since the last poll.
+    ///
+    /// This also adjusts the polling interval: it goes back to the base interval when
+    /// something changed, grows by half when nothing did, and doubles when the poll failed.
+    /// It never goes over the maximum interval.
+    pub fn poll(&mut self) -> Result<Vec<Change>, Box<dyn Error>> {
+        let next = match self.fetch() {
+            Ok(s) => s,
+            Err(e) => {
+                self.interval = (self.interval * 2).min(self.max_interval);
+                return Err(e);
+            }
+        };
+        let changes = match &self.snapshot {
+            Some(previous) => previous.diff(&next),
+            None => Vec::new(),
+        };
+        self.snapshot = Some(next);
+        self.interval = if changes.is_empty() {
+            self.interval.mul_f64(1.5).min(self.max_interval)
+        } else {
+            self.base_interval
+        };
+        Ok(changes)
+    }
+
+    /// Watch the event on a background thread and send every change over a channel.
+    ///
+    /// The thread stops once the receiver is dropped.
+    pub fn spawn(mut self) -> mpsc::Receiver<Change> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for change in &mut self {
+                if sender.send(change).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    fn fetch(&self) -> Result<Snapshot, Box<dyn Error>> {
+        let key = &self.event.event_key;
+        let mut snapshot = Snapshot::default();
+        for m in self.get(&format!("/event/{}/matches", key))?.iter() {
+            if let Some(m) = Match::from_json(m) {
+                snapshot.matches.insert(m.match_key.clone(), m);
+            }
+        }
+        for r in self.get(&format!("/event/{}/rankings", key))?.iter() {
+            if let Some(r) = Ranking::from_json(r) {
+                snapshot.ranks.insert(r.team_number, r.rank);
+            }
+        }
+        for a in self.get(&format!("/event/{}/alliances", key))?.iter() {
+            let name = match a["alliance_name"]
+                .as_str()
+                .or_else(|| a["alliance_key"].as_str())
+            {
+                Some(n) => n.to_string(),
+                None => continue,
+            };
+            snapshot.alliances.insert(name, alliance_teams(a));
+        }
+        for a in self.get(&format!("/event/{}/awards", key))?.iter() {
+            snapshot.awards.push(Award {
+                award_key: a["award_key"].as_str().unwrap_or("").to_string(),
+                award_name: a["award_name"].as_str().unwrap_or("").to_string(),
+                team_number: team_number(&a["team_key"]),
+                receiver_name: a["receiver_name"].as_str().map(|s| s.to_string()),
+            });
+        }
+        Ok(snapshot)
+    }
+
+    /// Fetch a list straight from the API. Lists the API does not have yet, which it answers
+    /// with a `404`, are empty.
+    fn get(&self, path: &str) -> Result<Vec<Value>, Box<dyn Error>> {
+        let resp = self.event.client.request(path)?;
+        if resp.status == 404 {
+            return Ok(Vec::new());
+        }
+        if !resp.is_success() {
+            return Err(format!("The API returned {}", resp.status).into());
+        }
+        match serde_json::from_str(&resp.body)? {
+            Value::Array(values) => Ok(values),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = Change;
+
+    /// Block until the next change. Failed polls are retried after backing off.
+    fn next(&mut self) -> Option<Change> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(change);
+            }
+            if self.snapshot.is_some() {
+                thread::sleep(self.interval);
+            }
+            if let Ok(changes) = self.poll() {
+                self.pending.extend(changes);
+            } else if self.snapshot.is_none() {
+                thread::sleep(self.interval);
+            }
+        }
+    }
+}
+
+fn team_number(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => n.as_u64().map(|n| n as u32),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The teams of an alliance, captain first.
+fn alliance_teams(alliance: &Value) -> Vec<u32> {
+    if let Some(keys) = alliance["team_keys"].as_array() {
+        return keys.iter().filter_map(team_number).collect();
+    }
+    ["captain", "pick1", "pick2", "pick3"]
+        .iter()
+        .filter_map(|role| {
+            team_number(&alliance[*role])
+                .or_else(|| team_number(&alliance[format!("{}_key", role)]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, Snapshot};
+    use crate::{Client, Match, Transport, TransportRequest, TransportResponse};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn played(key: &str, red_score: i32, blue_score: i32) -> Match {
+        Match {
+            match_key: key.to_string(),
+            match_start_time: Some("2019-12-07T15:00:00.000Z".to_string()),
+            red_score,
+            blue_score,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn diffs_snapshots() {
+        let mut before = Snapshot::default();
+        before
+            .matches
+            .insert("Q1".to_string(), played("Q1", 100, 80));
+        before.ranks.insert(16405, 2);
+        before.ranks.insert(8565, 1);
+
+        let mut after = before.clone();
+        after
+            .matches
+            .insert("Q1".to_string(), played("Q1", 100, 85));
+        after.matches.insert("Q2".to_string(), played("Q2", 50, 60));
+        after.matches.insert("Q3".to_string(), Match::default());
+        after.ranks.insert(16405, 1);
+        after.ranks.insert(8565, 2);
+        after
+            .alliances
+            .insert("Alliance 1".to_string(), vec![16405, 8565]);
+
+        let changes = before.diff(&after);
+        assert_eq!(changes.len(), 5);
+        assert!(
+            matches!(&changes[0], Change::ScoreCorrected { after, .. } if after.blue_score == 85)
+        );
+        assert!(matches!(&changes[1], Change::MatchScored(m) if m.match_key == "Q2"));
+        assert_eq!(
+            changes[2],
+            Change::RankChanged {
+                team_number: 16405,
+                from: Some(2),
+                to: 1
+            }
+        );
+        assert_eq!(
+            changes[4],
+            Change::AllianceSelected {
+                alliance: "Alliance 1".to_string(),
+                teams: vec![16405, 8565]
+            }
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+
+    /// Serves rankings that change on the third request, and rate limits the fourth.
+    #[derive(Debug, Default)]
+    struct LiveTransport {
+        polls: Mutex<u32>,
+    }
+
+    impl Transport for LiveTransport {
+        fn get(
+            &self,
+            request: &TransportRequest,
+        ) -> Result<TransportResponse, Box<dyn std::error::Error>> {
+            if !request.path.ends_with("/rankings") {
+                return Ok(TransportResponse {
+                    status: 404,
+                    body: "{}".to_string(),
+                    headers: Vec::new(),
+                });
+            }
+            let mut polls = self.polls.lock().unwrap();
+            *polls += 1;
+            let (status, rank) = match *polls {
+                1 | 2 => (200, 2),
+                3 => (200, 1),
+                _ => (429, 0),
+            };
+            let body = format!(
+                r#"[{{"team_key": "16405", "rank": {}, "wins": 0, "losses": 0, "ties": 0,
+                "opr": 0, "np_opr": 0, "highest_qual_score": 0, "ranking_points": 0,
+                "qualifying_points": 0, "tie_breaker_points": 0}}]"#,
+                rank
+            );
+            Ok(TransportResponse {
+                status,
+                body,
+                headers: Vec::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn polling_adapts_to_changes_and_rate_limits() {
+        let client = Client::new("api_key").with_transport(LiveTransport::default());
+        let mut watcher = client
+            .event("1920-TX-TRQ")
+            .watch(Duration::from_secs(10))
+            .max_interval(Duration::from_secs(30));
+
+        assert!(watcher.poll().unwrap().is_empty());
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(
+            watcher.interval(),
+            Duration::from_secs(22) + Duration::from_millis(500)
+        );
+
+        let changes = watcher.poll().unwrap();
+        assert_eq!(
+            changes,
+            vec![Change::RankChanged {
+                team_number: 16405,
+                from: Some(2),
+                to: 1
+            }]
+        );
+        assert_eq!(watcher.interval(), Duration::from_secs(10));
+
+        assert!(watcher.poll().is_err());
+        assert_eq!(watcher.interval(), Duration::from_secs(20));
+        assert_eq!(watcher.snapshot().unwrap().ranks[&16405], 1);
+    }
+}